@@ -0,0 +1,258 @@
+use std::fs::File;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use serde::Serialize;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{Decoder, DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader, Track};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+/// Decoded audio with its original format
+pub struct DecodedAudio {
+    /// Interleaved samples in the range -1.0 to 1.0
+    pub samples: Vec<f32>,
+    /// Sample rate in Hz
+    pub sample_rate: u32,
+    /// Number of interleaved channels
+    pub channels: u16,
+}
+
+/// Description of an audio track in a media file
+#[derive(Debug, Clone, Serialize)]
+pub struct AudioTrackInfo {
+    /// Index of the track among the audio tracks of the file
+    pub index: usize,
+    /// Short name of the codec, e.g. mp3 or aac
+    pub codec: String,
+    /// Language tag of the track, if the container has one
+    pub language: Option<String>,
+    /// Sample rate in Hz, if known before decoding
+    pub sample_rate: Option<u32>,
+    /// Number of channels, if known before decoding
+    pub channels: Option<u16>,
+}
+
+/// Lists the audio tracks of a media file.
+///
+/// # Arguments
+///
+/// * `path` - Audio or video file (WAV, MP3, M4A/AAC, OGG, FLAC, MP4, MKV)
+///
+/// # Returns
+///
+/// * `Result<Vec<AudioTrackInfo>, String>` - The audio tracks, or an error if the
+///   file can't be opened or its format is not supported
+pub fn audio_tracks(path: &Path) -> Result<Vec<AudioTrackInfo>, String> {
+    let format = open_format(path)?;
+    let codecs = symphonia::default::get_codecs();
+
+    Ok(audio_track_list(format.as_ref())
+        .into_iter()
+        .enumerate()
+        .map(|(index, track)| AudioTrackInfo {
+            index,
+            codec: codecs
+                .get_codec(track.codec_params.codec)
+                .map(|codec| codec.short_name.to_string())
+                .unwrap_or_else(|| "unknown".to_string()),
+            language: track.language.clone(),
+            sample_rate: track.codec_params.sample_rate,
+            channels: track.codec_params.channels.map(|channels| channels.count() as u16),
+        })
+        .collect())
+}
+
+/// Decodes one audio track of a media file into interleaved f32 samples.
+///
+/// # Arguments
+///
+/// * `path` - Audio or video file (WAV, MP3, M4A/AAC, OGG, FLAC, MP4, MKV)
+/// * `track` - Index of the audio track to decode, None for the first one
+///
+/// # Returns
+///
+/// * `Result<DecodedAudio, String>` - The decoded audio in its original sample rate
+///   and channel layout, or an error message
+pub fn decode_file(path: &Path, track: Option<usize>) -> Result<DecodedAudio, String> {
+    let mut decoder = AudioDecoder::open(path, track)?;
+    let mut samples = Vec::new();
+    while let Some(packet) = decoder.next_samples()? {
+        samples.extend_from_slice(&packet);
+    }
+
+    Ok(DecodedAudio {
+        samples,
+        sample_rate: decoder.sample_rate(),
+        channels: decoder.channels(),
+    })
+}
+
+/// AudioDecoder decodes one audio track of a media file packet by packet, so
+/// long recordings never have to fit in memory at once.
+pub struct AudioDecoder {
+    /// File being decoded, for error messages
+    path: String,
+    /// Container the packets are read from
+    format: Box<dyn FormatReader>,
+    /// Decoder of the selected track
+    decoder: Box<dyn Decoder>,
+    /// Id of the selected track in the container
+    track_id: u32,
+    /// Sample rate in Hz
+    sample_rate: u32,
+    /// Number of interleaved channels
+    channels: u16,
+    /// Number of frames of the track, if the container stores it
+    frames: Option<u64>,
+    /// Conversion buffer, reused unless a packet is larger than any before it
+    buffer: Option<SampleBuffer<f32>>,
+    /// Samples of the first packet, decoded when opening to learn the format
+    first_packet: Option<Vec<f32>>,
+}
+
+impl AudioDecoder {
+    /// Opens an audio track of a media file and decodes its first packet, so the
+    /// sample rate and channels are known before the rest is read.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Audio or video file (WAV, MP3, M4A/AAC, OGG, FLAC, MP4, MKV)
+    /// * `track` - Index of the audio track to decode, None for the first one
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, String>` - The decoder, or an error if the file can't be read or
+    ///   has no such audio track
+    pub fn open(path: &Path, track: Option<usize>) -> Result<Self, String> {
+        let format = open_format(path)?;
+
+        let (track_id, codec_params) = {
+            let tracks = audio_track_list(format.as_ref());
+            let index = track.unwrap_or(0);
+            let selected = tracks.get(index).ok_or_else(|| {
+                if tracks.is_empty() {
+                    format!("{} has no audio track", path.display())
+                } else {
+                    format!("{} has no audio track {} ({} available)", path.display(), index, tracks.len())
+                }
+            })?;
+            (selected.id, selected.codec_params.clone())
+        };
+
+        let decoder = symphonia::default::get_codecs()
+            .make(&codec_params, &DecoderOptions::default())
+            .map_err(|e| format!("Unsupported codec in {}: {}", path.display(), e))?;
+
+        let mut audio = Self {
+            path: path.display().to_string(),
+            format,
+            decoder,
+            track_id,
+            sample_rate: codec_params.sample_rate.unwrap_or(0),
+            channels: codec_params.channels.map(|c| c.count() as u16).unwrap_or(0),
+            frames: codec_params.n_frames,
+            buffer: None,
+            first_packet: None,
+        };
+        audio.first_packet = audio.decode_packet()?;
+        if audio.sample_rate == 0 || audio.channels == 0 {
+            return Err(format!("{} contains no decodable audio", audio.path));
+        }
+        Ok(audio)
+    }
+
+    /// Sample rate of the decoded audio in Hz.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Number of interleaved channels of the decoded audio.
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    /// Duration of the track in seconds, if the container stores it.
+    pub fn duration_seconds(&self) -> Option<f32> {
+        self.frames.map(|frames| frames as f32 / self.sample_rate as f32)
+    }
+
+    /// Decodes the next packet of the track.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<Vec<f32>>, String>` - Interleaved samples of the packet, None at
+    ///   the end of the file, or an error message
+    pub fn next_samples(&mut self) -> Result<Option<Vec<f32>>, String> {
+        if let Some(samples) = self.first_packet.take() {
+            return Ok(Some(samples));
+        }
+        self.decode_packet()
+    }
+
+    /// Reads and decodes packets until one of the selected track has audio.
+    fn decode_packet(&mut self) -> Result<Option<Vec<f32>>, String> {
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                Err(SymphoniaError::IoError(e)) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+                Err(e) => return Err(format!("Failed to read {}: {}", self.path, e)),
+            };
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+
+            let decoded = match self.decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                // A corrupt packet only loses a few milliseconds of audio
+                Err(SymphoniaError::DecodeError(e)) => {
+                    println!("Skipping undecodable packet in {}: {}", self.path, e);
+                    continue;
+                }
+                Err(e) => return Err(format!("Failed to decode {}: {}", self.path, e)),
+            };
+
+            let spec = *decoded.spec();
+            self.sample_rate = spec.rate;
+            self.channels = spec.channels.count() as u16;
+
+            // Reuse the sample buffer unless a packet is larger than any before it
+            let buffer = match &mut self.buffer {
+                Some(buffer) if buffer.capacity() >= decoded.capacity() * spec.channels.count() => buffer,
+                buffer => buffer.insert(SampleBuffer::new(decoded.capacity() as u64, spec)),
+            };
+            buffer.copy_interleaved_ref(decoded);
+            return Ok(Some(buffer.samples().to_vec()));
+        }
+    }
+}
+
+/// Probes the container format of a file.
+fn open_format(path: &Path) -> Result<Box<dyn FormatReader>, String> {
+    let file = File::open(path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+
+    // The extension speeds up probing but the content decides
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|ext| ext.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    let probed = symphonia::default::get_probe()
+        .format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())
+        .map_err(|e| format!("Unsupported format of {}: {}", path.display(), e))?;
+    Ok(probed.format)
+}
+
+/// Returns the tracks of a container that carry audio, skipping video and subtitles.
+fn audio_track_list(format: &dyn FormatReader) -> Vec<&Track> {
+    format
+        .tracks()
+        .iter()
+        .filter(|track| track.codec_params.codec != CODEC_TYPE_NULL && track.codec_params.sample_rate.is_some())
+        .collect()
+}
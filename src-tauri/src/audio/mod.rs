@@ -3,4 +3,6 @@ pub mod recorder;
 #[allow(dead_code)]
 pub mod processor;
 #[allow(dead_code)]
+pub mod decoder;
+#[allow(dead_code)]
 pub mod storage; 
\ No newline at end of file
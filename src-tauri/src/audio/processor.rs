@@ -98,6 +98,46 @@ impl AudioProcessor {
         }
     }
 
+    /// Returns a processed copy of the buffered audio without consuming it, e.g.
+    /// for an interim transcription of a window that is still growing.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Vec<f32>>` - Processed audio, or None if the buffer is empty
+    pub fn pending(&self) -> Option<Vec<f32>> {
+        let samples = self.buffer.lock().unwrap().clone();
+        if samples.is_empty() {
+            return None;
+        }
+
+        Some(self.convert(samples))
+    }
+
+    /// Processes whatever is left in the buffer, even if it is less than the
+    /// minimum, e.g. at the end of a file.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Vec<f32>>` - Processed audio, or None if the buffer is empty
+    pub fn flush(&self) -> Option<Vec<f32>> {
+        let samples: Vec<f32> = self.buffer.lock().unwrap().drain(..).collect();
+        if samples.is_empty() {
+            return None;
+        }
+
+        Some(self.convert(samples))
+    }
+
+    /// Resamples audio to the target format if it differs from the source format.
+    fn convert(&self, samples: Vec<f32>) -> Vec<f32> {
+        if self.config.source_sample_rate != self.config.target_sample_rate ||
+           self.config.source_channels != self.config.target_channels {
+            self.resample(&samples)
+        } else {
+            samples
+        }
+    }
+
     /// Resamples audio from source to target sample rate and channels.
     ///
     /// # Arguments
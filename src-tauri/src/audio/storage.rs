@@ -1,5 +1,17 @@
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use hound;
+use serde::Serialize;
+
+use crate::encryption::{self, Encryptor, ENCRYPTED_EXTENSION};
+
+/// Extension used for session recordings
+const RECORDING_EXTENSION: &str = "wav";
+/// Extension used for session transcripts
+const TRANSCRIPT_EXTENSION: &str = "txt";
 
 /// Configuration for audio storage
 #[derive(Clone)]
@@ -14,6 +26,12 @@ pub struct StorageConfig {
     pub output_channels: u16,
     /// Bits per sample for the output WAV file
     pub output_bits_per_sample: u16,
+    /// Directory where per-session recordings are kept
+    pub recordings_dir: String,
+    /// Retention policy applied to the recordings directory
+    pub retention: RetentionPolicy,
+    /// Encryptor for files written to disk (files are plaintext if None)
+    pub encryptor: Option<Encryptor>,
 }
 
 impl Default for StorageConfig {
@@ -24,6 +42,84 @@ impl Default for StorageConfig {
             output_sample_rate: 44100,
             output_channels: 1,
             output_bits_per_sample: 16,
+            recordings_dir: "recordings".to_string(),
+            retention: RetentionPolicy::default(),
+            encryptor: None,
+        }
+    }
+}
+
+/// Limits applied to the recordings kept on disk
+#[derive(Clone, Default)]
+pub struct RetentionPolicy {
+    /// Maximum total size of all recordings in bytes
+    pub max_total_bytes: Option<u64>,
+    /// Maximum age of a recording
+    pub max_age: Option<Duration>,
+    /// Whether recordings with a saved transcript should be deleted
+    pub delete_after_transcription: bool,
+}
+
+/// Why a recording is selected for deletion
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetentionReason {
+    /// The recording has been transcribed and audio is not kept afterwards
+    Transcribed,
+    /// The recording is older than the maximum age
+    Expired,
+    /// The recordings directory exceeds the disk quota
+    OverQuota,
+}
+
+/// A recording selected for deletion by the retention policy
+#[derive(Debug, Clone, Serialize)]
+pub struct RetentionAction {
+    /// Path of the recording, or of its transcript if the audio is already gone
+    pub path: PathBuf,
+    /// All files deleted: the recording and, unless only the audio is removed
+    /// after transcription, its transcript
+    pub files: Vec<PathBuf>,
+    /// Size of the deleted files in bytes
+    pub size_bytes: u64,
+    /// Why the recording is deleted
+    pub reason: RetentionReason,
+}
+
+/// A session found in the recordings directory: its recording, its transcript, or both
+struct RecordingEntry {
+    /// Recording, None if only the transcript is left
+    recording: Option<StoredFile>,
+    /// Transcript saved next to the recording
+    transcript: Option<StoredFile>,
+    /// When the session was saved
+    modified: SystemTime,
+}
+
+/// A file belonging to a session
+struct StoredFile {
+    path: PathBuf,
+    size_bytes: u64,
+}
+
+impl RecordingEntry {
+    fn size_bytes(&self) -> u64 {
+        self.files().map(|file| file.size_bytes).sum()
+    }
+
+    fn files(&self) -> impl Iterator<Item = &StoredFile> {
+        self.recording.iter().chain(self.transcript.iter())
+    }
+
+    /// Selects the whole session for deletion.
+    fn into_action(self, reason: RetentionReason) -> RetentionAction {
+        let size_bytes = self.size_bytes();
+        let files: Vec<PathBuf> = self.files().map(|file| file.path.clone()).collect();
+        RetentionAction {
+            path: files[0].clone(),
+            files,
+            size_bytes,
+            reason,
         }
     }
 }
@@ -71,6 +167,129 @@ impl AudioStorage {
             return Ok(());
         }
 
+        self.write_wav(Path::new(&self.config.output_path)).map(|_| ())
+    }
+
+    /// Saves the recorded audio as a new session file in the recordings directory.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<PathBuf>, String>` - Path of the saved recording, None if saving
+    ///   is disabled or nothing was recorded
+    pub fn save_session(&self) -> Result<Option<PathBuf>, String> {
+        if !self.config.save_to_file || self.recorded_samples.lock().unwrap().is_empty() {
+            return Ok(None);
+        }
+
+        fs::create_dir_all(&self.config.recordings_dir)
+            .map_err(|e| format!("Failed to create recordings directory: {}", e))?;
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or(0);
+        let dir = Path::new(&self.config.recordings_dir);
+        // Sessions ending in the same millisecond get a counter instead of overwriting each other
+        let mut name = format!("session-{}", timestamp);
+        let mut counter = 1;
+        while dir.join(&name).with_extension(RECORDING_EXTENSION).exists()
+            || with_encrypted_extension(&dir.join(&name).with_extension(RECORDING_EXTENSION)).exists()
+        {
+            name = format!("session-{}-{}", timestamp, counter);
+            counter += 1;
+        }
+        let path = dir.join(name).with_extension(RECORDING_EXTENSION);
+
+        self.write_wav(&path).map(Some)
+    }
+
+    /// Saves the transcript of a recording next to it.
+    ///
+    /// # Arguments
+    ///
+    /// * `recording` - Path of the recording the transcript belongs to
+    /// * `text` - Transcribed text
+    ///
+    /// # Returns
+    ///
+    /// * `Result<PathBuf, String>` - Path of the saved transcript
+    pub fn save_transcript(&self, recording: &Path, text: &str) -> Result<PathBuf, String> {
+        let path = recording_stem(recording).with_extension(TRANSCRIPT_EXTENSION);
+        let path = self.write_file(&path, text.as_bytes())?;
+        println!("Transcript written to {}", path.display());
+        Ok(path)
+    }
+
+    /// Writes a stored file back out as plaintext.
+    ///
+    /// Encrypted files are decrypted with the configured key; plaintext files are copied.
+    /// Only recordings and transcripts in the recordings directory can be exported, and
+    /// an existing destination is never overwritten.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Recording or transcript written by this storage
+    /// * `destination` - Where the plaintext should be written; must not exist yet
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - Ok if successful, Err with error message otherwise
+    pub fn export(&self, source: &Path, destination: &Path) -> Result<(), String> {
+        let recordings_dir = fs::canonicalize(&self.config.recordings_dir)
+            .map_err(|e| format!("Failed to resolve recordings directory: {}", e))?;
+        let source = fs::canonicalize(source)
+            .map_err(|e| format!("Failed to resolve {}: {}", source.display(), e))?;
+        if source.parent() != Some(recordings_dir.as_path()) || session_stem(&source).is_none() {
+            return Err(format!("{} is not a recording or transcript", source.display()));
+        }
+
+        let data = fs::read(&source)
+            .map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+
+        let plaintext = if encryption::is_encrypted(&data) {
+            match &self.config.encryptor {
+                Some(encryptor) => encryptor.decrypt(&data)?,
+                None => return Err(format!("{} is encrypted but no key is configured", source.display())),
+            }
+        } else {
+            data
+        };
+
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(destination)
+            .map_err(|e| format!("Failed to create {}: {}", destination.display(), e))?;
+        file.write_all(&plaintext)
+            .map_err(|e| format!("Failed to write {}: {}", destination.display(), e))?;
+        println!("Exported {} to {}", source.display(), destination.display());
+        Ok(())
+    }
+
+    /// Writes data to the given path, encrypting it if an encryptor is configured.
+    ///
+    /// Encrypted files get an additional `.enc` extension.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<PathBuf, String>` - Path of the written file
+    fn write_file(&self, path: &Path, data: &[u8]) -> Result<PathBuf, String> {
+        let (path, contents) = match &self.config.encryptor {
+            Some(encryptor) => (with_encrypted_extension(path), encryptor.encrypt(data)?),
+            None => (path.to_path_buf(), data.to_vec()),
+        };
+
+        fs::write(&path, contents)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        Ok(path)
+    }
+
+    /// Writes the buffered samples to a WAV file at the given path.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<PathBuf, String>` - Path of the written file
+    fn write_wav(&self, path: &Path) -> Result<PathBuf, String> {
         // Create WAV spec
         let spec = hound::WavSpec {
             channels: self.config.output_channels,
@@ -80,7 +299,8 @@ impl AudioStorage {
         };
 
         // Create WAV writer
-        let mut writer = match hound::WavWriter::create(&self.config.output_path, spec) {
+        let mut encoded = Cursor::new(Vec::new());
+        let mut writer = match hound::WavWriter::new(&mut encoded, spec) {
             Ok(writer) => writer,
             Err(err) => return Err(format!("Failed to create WAV writer: {}", err)),
         };
@@ -101,8 +321,123 @@ impl AudioStorage {
             return Err(format!("Failed to finalize WAV file: {}", err));
         }
 
-        println!("WAV file written to {}", self.config.output_path);
-        Ok(())
+        let path = self.write_file(path, &encoded.into_inner())?;
+        println!("WAV file written to {}", path.display());
+        Ok(path)
+    }
+
+    /// Lists the recordings the retention policy would delete, without deleting them.
+    ///
+    /// Sessions are considered oldest first, each with its recording and transcript.
+    /// A recording is selected when it has been transcribed (and audio is not kept
+    /// after transcription), in which case only the audio is deleted and the transcript
+    /// stays. A session is deleted entirely when it is older than the maximum age, or
+    /// while the remaining sessions, transcripts included, exceed the disk quota.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<RetentionAction>, String>` - Recordings that would be deleted
+    pub fn plan_retention(&self) -> Result<Vec<RetentionAction>, String> {
+        let policy = &self.config.retention;
+        let mut sessions = self.list_recordings()?;
+        sessions.sort_by_key(|entry| entry.modified);
+
+        let now = SystemTime::now();
+        let mut actions = Vec::new();
+        let mut kept = Vec::new();
+
+        for mut entry in sessions {
+            let age = now.duration_since(entry.modified).unwrap_or_default();
+            if policy.max_age.is_some_and(|max_age| age > max_age) {
+                actions.push(entry.into_action(RetentionReason::Expired));
+                continue;
+            }
+            if policy.delete_after_transcription && entry.transcript.is_some() {
+                if let Some(recording) = entry.recording.take() {
+                    actions.push(RetentionAction {
+                        path: recording.path.clone(),
+                        files: vec![recording.path],
+                        size_bytes: recording.size_bytes,
+                        reason: RetentionReason::Transcribed,
+                    });
+                }
+            }
+            kept.push(entry);
+        }
+
+        if let Some(max_total_bytes) = policy.max_total_bytes {
+            let mut total: u64 = kept.iter().map(|entry| entry.size_bytes()).sum();
+            for entry in kept {
+                if total <= max_total_bytes {
+                    break;
+                }
+                total -= entry.size_bytes();
+                actions.push(entry.into_action(RetentionReason::OverQuota));
+            }
+        }
+
+        Ok(actions)
+    }
+
+    /// Applies the retention policy, deleting the recordings it selects.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<RetentionAction>, String>` - Recordings that were deleted
+    pub fn enforce_retention(&self) -> Result<Vec<RetentionAction>, String> {
+        let actions = self.plan_retention()?;
+        for action in &actions {
+            for path in &action.files {
+                fs::remove_file(path)
+                    .map_err(|e| format!("Failed to delete {}: {}", path.display(), e))?;
+            }
+            println!("Deleted recording {} ({:?})", action.path.display(), action.reason);
+        }
+        Ok(actions)
+    }
+
+    /// Lists the sessions currently stored in the recordings directory.
+    fn list_recordings(&self) -> Result<Vec<RecordingEntry>, String> {
+        let dir = Path::new(&self.config.recordings_dir);
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let entries = fs::read_dir(dir)
+            .map_err(|e| format!("Failed to read recordings directory: {}", e))?;
+
+        let mut sessions: Vec<(PathBuf, RecordingEntry)> = Vec::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let (stem, is_recording) = match session_stem(&path) {
+                Some(session) => session,
+                None => continue,
+            };
+            let metadata = match entry.metadata() {
+                Ok(metadata) if metadata.is_file() => metadata,
+                _ => continue,
+            };
+            let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
+            let file = StoredFile { path, size_bytes: metadata.len() };
+
+            let index = match sessions.iter().position(|(existing, _)| *existing == stem) {
+                Some(index) => index,
+                None => {
+                    sessions.push((stem, RecordingEntry { recording: None, transcript: None, modified }));
+                    sessions.len() - 1
+                }
+            };
+            let session = &mut sessions[index].1;
+            // The recording is saved first, so its time is the session's
+            session.modified = session.modified.min(modified);
+            if is_recording {
+                session.recording = Some(file);
+            } else {
+                session.transcript = Some(file);
+            }
+        }
+
+        Ok(sessions.into_iter().map(|(_, session)| session).collect())
     }
 
     /// Clears the recorded samples buffer.
@@ -110,4 +445,148 @@ impl AudioStorage {
         let mut buffer = self.recorded_samples.lock().unwrap();
         buffer.clear();
     }
-} 
\ No newline at end of file
+}
+
+/// Appends the encrypted file extension to a path (`a.wav` becomes `a.wav.enc`).
+fn with_encrypted_extension(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".");
+    name.push(ENCRYPTED_EXTENSION);
+    PathBuf::from(name)
+}
+
+/// Strips the recording extension, plain or encrypted, from a path.
+///
+/// Returns the path unchanged if it is not a recording.
+fn recording_stem(path: &Path) -> PathBuf {
+    strip_extension(path, RECORDING_EXTENSION).unwrap_or_else(|| path.to_path_buf())
+}
+
+/// Splits a file of the recordings directory into its session stem and whether
+/// it is the recording (rather than the transcript).
+///
+/// Returns None for files that are neither.
+fn session_stem(path: &Path) -> Option<(PathBuf, bool)> {
+    if let Some(stem) = strip_extension(path, RECORDING_EXTENSION) {
+        return Some((stem, true));
+    }
+    strip_extension(path, TRANSCRIPT_EXTENSION).map(|stem| (stem, false))
+}
+
+/// Strips an extension, plain or with the encrypted extension appended, from a path.
+fn strip_extension(path: &Path, extension: &str) -> Option<PathBuf> {
+    let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    let plain = format!(".{}", extension);
+    let encrypted = format!(".{}.{}", extension, ENCRYPTED_EXTENSION);
+
+    name.strip_suffix(&encrypted)
+        .or_else(|| name.strip_suffix(&plain))
+        .map(|stem| path.with_file_name(stem))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    /// Creates an empty recordings directory unique to the test.
+    fn recordings_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("storage-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write(dir: &Path, name: &str, size: usize, age: Duration) {
+        let path = dir.join(name);
+        fs::write(&path, vec![0u8; size]).unwrap();
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() - age)
+            .unwrap();
+    }
+
+    fn storage(dir: &Path, retention: RetentionPolicy) -> AudioStorage {
+        AudioStorage::with_config(StorageConfig {
+            recordings_dir: dir.to_string_lossy().to_string(),
+            retention,
+            ..StorageConfig::default()
+        })
+    }
+
+    #[test]
+    fn expired_sessions_are_deleted_with_their_transcript() {
+        let dir = recordings_dir("expired");
+        let day = Duration::from_secs(24 * 60 * 60);
+        write(&dir, "session-1.wav", 100, day * 10);
+        write(&dir, "session-1.txt", 10, day * 10);
+        write(&dir, "session-2.wav", 100, day);
+
+        let storage = storage(&dir, RetentionPolicy { max_age: Some(day * 7), ..Default::default() });
+        let actions = storage.enforce_retention().unwrap();
+
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].reason, RetentionReason::Expired);
+        assert_eq!(actions[0].size_bytes, 110);
+        assert!(!dir.join("session-1.wav").exists());
+        assert!(!dir.join("session-1.txt").exists());
+        assert!(dir.join("session-2.wav").exists());
+    }
+
+    #[test]
+    fn transcripts_stay_after_transcription_and_count_toward_the_quota() {
+        let dir = recordings_dir("transcribed");
+        let minute = Duration::from_secs(60);
+        write(&dir, "session-1.wav", 100, minute * 3);
+        write(&dir, "session-1.txt", 50, minute * 3);
+        write(&dir, "session-2.wav", 100, minute);
+
+        let storage = storage(
+            &dir,
+            RetentionPolicy {
+                max_total_bytes: Some(120),
+                delete_after_transcription: true,
+                ..Default::default()
+            },
+        );
+        let actions = storage.plan_retention().unwrap();
+
+        // The audio of session 1 goes first; with its transcript the rest is still over quota
+        assert_eq!(actions.len(), 2);
+        assert_eq!(actions[0].reason, RetentionReason::Transcribed);
+        assert_eq!(actions[0].files, vec![dir.join("session-1.wav")]);
+        assert_eq!(actions[1].reason, RetentionReason::OverQuota);
+        assert_eq!(actions[1].files, vec![dir.join("session-1.txt")]);
+    }
+
+    #[test]
+    fn export_is_limited_to_the_recordings_directory() {
+        let dir = recordings_dir("export");
+        write(&dir, "session-1.txt", 10, Duration::ZERO);
+        let outside = dir.with_extension("txt");
+        fs::write(&outside, "secret").unwrap();
+        let storage = storage(&dir, RetentionPolicy::default());
+
+        let destination = dir.join("export.txt");
+        let _ = fs::remove_file(&destination);
+        assert!(storage.export(&outside, &destination).is_err());
+        assert!(storage.export(&dir.join("../").join(outside.file_name().unwrap()), &destination).is_err());
+        storage.export(&dir.join("session-1.txt"), &destination).unwrap();
+        // An existing file is never overwritten
+        assert!(storage.export(&dir.join("session-1.txt"), &destination).is_err());
+    }
+
+    #[test]
+    fn sessions_saved_at_the_same_time_get_distinct_names() {
+        let dir = recordings_dir("names");
+        let storage = storage(&dir, RetentionPolicy::default());
+        storage.add_samples(&[0.0; 16]);
+
+        let first = storage.save_session().unwrap().unwrap();
+        let second = storage.save_session().unwrap().unwrap();
+        assert_ne!(first, second);
+        assert!(first.exists() && second.exists());
+    }
+}
@@ -0,0 +1,484 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::audio::decoder;
+use crate::audio::processor::{AudioProcessor, ProcessorConfig};
+use crate::transcription::engine::EngineKind;
+use crate::transcription::service::{TranscriptionConfig, TranscriptionService};
+
+/// One model and parameter set to benchmark
+#[derive(Clone)]
+pub struct BenchmarkRun {
+    /// Name of the run in the report
+    pub name: String,
+    /// Transcription configuration with the run's model and parameters
+    pub transcription: TranscriptionConfig,
+}
+
+/// Configuration for the real-time factor benchmark
+#[derive(Clone)]
+pub struct BenchmarkConfig {
+    /// Directory with the WAV files to transcribe
+    pub audio_dir: PathBuf,
+    /// Directory the reports are written to
+    pub output_dir: PathBuf,
+    /// Length of the windows the audio is transcribed in, in seconds
+    pub window_seconds: f32,
+    /// Runs to compare, each over all files
+    pub runs: Vec<BenchmarkRun>,
+}
+
+/// Machine the benchmark ran on, to compare reports across machines
+#[derive(Debug, Clone, Serialize)]
+pub struct MachineInfo {
+    /// Operating system
+    pub os: String,
+    /// CPU architecture
+    pub arch: String,
+    /// Number of logical CPUs available to the process
+    pub cpus: usize,
+}
+
+/// Result of transcribing one file in one run
+#[derive(Debug, Clone, Serialize)]
+pub struct FileResult {
+    /// Name of the run
+    pub run: String,
+    /// File that was transcribed
+    pub file: String,
+    /// Duration of the audio in seconds
+    pub audio_seconds: f32,
+    /// Time spent transcribing in seconds
+    pub processing_seconds: f32,
+    /// Processing time divided by audio duration; below 1.0 keeps up with live audio
+    pub real_time_factor: f32,
+    /// Median time to transcribe a window, in milliseconds
+    pub latency_p50_ms: f32,
+    /// 90th percentile of the time to transcribe a window, in milliseconds
+    pub latency_p90_ms: f32,
+    /// 99th percentile of the time to transcribe a window, in milliseconds
+    pub latency_p99_ms: f32,
+    /// Peak resident memory the run used up to and including this file, above the
+    /// memory before the run, in bytes (None where not measured)
+    pub peak_memory_bytes: Option<u64>,
+    /// Transcribed text
+    pub text: String,
+}
+
+/// Totals of one run over all files
+#[derive(Debug, Clone, Serialize)]
+pub struct RunSummary {
+    /// Name of the run
+    pub run: String,
+    /// Model used, or the engine if it has no model file
+    pub model: String,
+    /// Number of inference threads, None for the default
+    pub threads: Option<usize>,
+    /// Time to load and warm up the model in seconds
+    pub load_seconds: f32,
+    /// Number of files transcribed
+    pub files: usize,
+    /// Total duration of the audio in seconds
+    pub audio_seconds: f32,
+    /// Total time spent transcribing in seconds
+    pub processing_seconds: f32,
+    /// Total processing time divided by total audio duration
+    pub real_time_factor: f32,
+    /// Median time to transcribe a window, over all files, in milliseconds
+    pub latency_p50_ms: f32,
+    /// 90th percentile over all files, in milliseconds
+    pub latency_p90_ms: f32,
+    /// 99th percentile over all files, in milliseconds
+    pub latency_p99_ms: f32,
+    /// Peak resident memory the run used, model included, above the memory before
+    /// the run, in bytes (None where not measured)
+    pub peak_memory_bytes: Option<u64>,
+}
+
+/// Report of a benchmark
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchmarkReport {
+    /// Unix time the benchmark started at
+    pub started_at: u64,
+    /// Machine the benchmark ran on
+    pub machine: MachineInfo,
+    /// Length of the transcribed windows in seconds
+    pub window_seconds: f32,
+    /// Totals of each run
+    pub runs: Vec<RunSummary>,
+    /// Result of each file in each run
+    pub files: Vec<FileResult>,
+}
+
+/// Audio of one benchmark file, resampled for the model
+struct BenchmarkAudio {
+    /// File name, as shown in the report
+    name: String,
+    /// Samples at the transcription sample rate
+    samples: Vec<f32>,
+}
+
+/// Runs the benchmark: every run transcribes every WAV file in the audio directory.
+///
+/// Audio is decoded and resampled once up front, so only transcription is timed.
+///
+/// # Arguments
+///
+/// * `config` - Audio directory, window length and runs
+///
+/// # Returns
+///
+/// * `Result<BenchmarkReport, String>` - The report, or an error message if the audio
+///   can't be read or a model fails to load
+pub fn run(config: &BenchmarkConfig) -> Result<BenchmarkReport, String> {
+    if config.runs.is_empty() {
+        return Err("No benchmark runs configured".to_string());
+    }
+    let started_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let sample_rate = config.runs[0].transcription.sample_rate as u32;
+    let audio = load_audio(&config.audio_dir, sample_rate)?;
+    println!("Benchmarking {} runs over {} files", config.runs.len(), audio.len());
+
+    let mut runs = Vec::new();
+    let mut files = Vec::new();
+    for run in &config.runs {
+        let (summary, results) = benchmark_run(run, &audio, config.window_seconds)?;
+        println!(
+            "{}: real-time factor {:.3}, p50 {:.0} ms, p90 {:.0} ms",
+            summary.run, summary.real_time_factor, summary.latency_p50_ms, summary.latency_p90_ms
+        );
+        runs.push(summary);
+        files.extend(results);
+    }
+
+    Ok(BenchmarkReport {
+        started_at,
+        machine: MachineInfo {
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            cpus: std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+        },
+        window_seconds: config.window_seconds,
+        runs,
+        files,
+    })
+}
+
+/// Loads the model of a run and transcribes all files with it.
+fn benchmark_run(
+    run: &BenchmarkRun,
+    audio: &[BenchmarkAudio],
+    window_seconds: f32,
+) -> Result<(RunSummary, Vec<FileResult>), String> {
+    let config = &run.transcription;
+    println!("Run {}: loading {}", run.name, config.model_path);
+
+    let mut service = TranscriptionService::with_config(config.clone());
+    service.prepare()?;
+    let memory = MemorySampler::start();
+    let load_started = Instant::now();
+    let mut engine = TranscriptionService::create_engine(config)?;
+    if config.warm_up {
+        TranscriptionService::warm_up(engine.as_mut(), config);
+    }
+    service.set_engine(engine, config);
+    let load_seconds = load_started.elapsed().as_secs_f32();
+
+    let window_samples = ((window_seconds * config.sample_rate as f32) as usize).max(1);
+    let mut results = Vec::new();
+    let mut all_latencies = Vec::new();
+    for file in audio {
+        service.reset_context();
+
+        let mut latencies = Vec::new();
+        let mut text = String::new();
+        for window in file.samples.chunks(window_samples) {
+            let started = Instant::now();
+            let segments = service.transcribe(window).unwrap_or_default();
+            latencies.push(started.elapsed().as_secs_f32());
+            text.extend(segments.iter().map(|segment| segment.text.as_str()));
+        }
+
+        let audio_seconds = file.samples.len() as f32 / config.sample_rate as f32;
+        let processing_seconds: f32 = latencies.iter().sum();
+        results.push(FileResult {
+            run: run.name.clone(),
+            file: file.name.clone(),
+            audio_seconds,
+            processing_seconds,
+            real_time_factor: ratio(processing_seconds, audio_seconds),
+            latency_p50_ms: percentile(&latencies, 50.0) * 1000.0,
+            latency_p90_ms: percentile(&latencies, 90.0) * 1000.0,
+            latency_p99_ms: percentile(&latencies, 99.0) * 1000.0,
+            peak_memory_bytes: memory.used_bytes(),
+            text: text.trim().to_string(),
+        });
+        all_latencies.extend(latencies);
+    }
+
+    let audio_seconds: f32 = results.iter().map(|r| r.audio_seconds).sum();
+    let processing_seconds: f32 = results.iter().map(|r| r.processing_seconds).sum();
+    let summary = RunSummary {
+        run: run.name.clone(),
+        model: match config.engine {
+            EngineKind::Whisper => config.model_path.clone(),
+            EngineKind::Mock => "mock".to_string(),
+        },
+        threads: config.decoding.threads,
+        load_seconds,
+        files: results.len(),
+        audio_seconds,
+        processing_seconds,
+        real_time_factor: ratio(processing_seconds, audio_seconds),
+        latency_p50_ms: percentile(&all_latencies, 50.0) * 1000.0,
+        latency_p90_ms: percentile(&all_latencies, 90.0) * 1000.0,
+        latency_p99_ms: percentile(&all_latencies, 99.0) * 1000.0,
+        peak_memory_bytes: memory.used_bytes(),
+    };
+    Ok((summary, results))
+}
+
+/// Decodes and resamples all WAV files in a directory, in name order.
+fn load_audio(dir: &Path, sample_rate: u32) -> Result<Vec<BenchmarkAudio>, String> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read benchmark directory {}: {}", dir.display(), e))?;
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("wav"))
+        })
+        .collect();
+    paths.sort();
+    if paths.is_empty() {
+        return Err(format!("No WAV files in {}", dir.display()));
+    }
+
+    paths
+        .iter()
+        .map(|path| {
+            let decoded = decoder::decode_file(path, None)?;
+            // Same resampling as live and file transcription
+            let processor = AudioProcessor::with_config(ProcessorConfig {
+                target_sample_rate: sample_rate,
+                target_channels: 1,
+                source_sample_rate: decoded.sample_rate,
+                source_channels: decoded.channels,
+                min_samples_for_processing: usize::MAX,
+                max_buffer_size: usize::MAX,
+            });
+            processor.process(decoded.samples);
+            Ok(BenchmarkAudio {
+                name: path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                samples: processor.flush().unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// Writes the report as JSON and as CSV with one row per file and run, followed
+/// by one row per run with the file column set to `*`.
+///
+/// # Arguments
+///
+/// * `report` - The report to write
+/// * `dir` - Directory to write `benchmark-<time>.json` and `.csv` to
+///
+/// # Returns
+///
+/// * `Result<(PathBuf, PathBuf), String>` - Paths of the JSON and CSV files
+pub fn write_report(report: &BenchmarkReport, dir: &Path) -> Result<(PathBuf, PathBuf), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let stem = dir.join(format!("benchmark-{}", report.started_at));
+
+    let json_path = stem.with_extension("json");
+    let json = serde_json::to_string_pretty(report).map_err(|e| format!("Failed to serialize report: {}", e))?;
+    fs::write(&json_path, json).map_err(|e| format!("Failed to write {}: {}", json_path.display(), e))?;
+
+    let mut csv = String::from(
+        "run,file,audio_seconds,processing_seconds,real_time_factor,latency_p50_ms,latency_p90_ms,latency_p99_ms,peak_memory_bytes,text\n",
+    );
+    let memory = |bytes: Option<u64>| bytes.map(|b| b.to_string()).unwrap_or_default();
+    for file in &report.files {
+        csv.push_str(&format!(
+            "{},{},{:.3},{:.3},{:.4},{:.1},{:.1},{:.1},{},{}\n",
+            csv_field(&file.run),
+            csv_field(&file.file),
+            file.audio_seconds,
+            file.processing_seconds,
+            file.real_time_factor,
+            file.latency_p50_ms,
+            file.latency_p90_ms,
+            file.latency_p99_ms,
+            memory(file.peak_memory_bytes),
+            csv_field(&file.text),
+        ));
+    }
+    for run in &report.runs {
+        csv.push_str(&format!(
+            "{},*,{:.3},{:.3},{:.4},{:.1},{:.1},{:.1},{},\n",
+            csv_field(&run.run),
+            run.audio_seconds,
+            run.processing_seconds,
+            run.real_time_factor,
+            run.latency_p50_ms,
+            run.latency_p90_ms,
+            run.latency_p99_ms,
+            memory(run.peak_memory_bytes),
+        ));
+    }
+    let csv_path = stem.with_extension("csv");
+    fs::write(&csv_path, csv).map_err(|e| format!("Failed to write {}: {}", csv_path.display(), e))?;
+
+    Ok((json_path, csv_path))
+}
+
+/// Measures the peak resident memory a run uses, as tracked by the kernel, so
+/// short spikes within a window count too.
+///
+/// Memory is measured against the process before the run, so runs that load
+/// different models can be compared within one benchmark. On Linux the peak is
+/// reset at the start of every run; elsewhere it can't be, and a run only shows
+/// the memory it uses beyond the peak of the runs before it.
+struct MemorySampler {
+    /// Resident memory before the run, in bytes
+    baseline: Option<u64>,
+}
+
+impl MemorySampler {
+    /// Takes the baseline; call before the model is loaded.
+    fn start() -> Self {
+        Self { baseline: reset_peak_memory().or_else(peak_memory_bytes) }
+    }
+
+    /// Peak memory above the baseline so far, in bytes.
+    fn used_bytes(&self) -> Option<u64> {
+        Some(peak_memory_bytes()?.saturating_sub(self.baseline?))
+    }
+}
+
+/// Resets the peak resident memory of the process to the current one.
+///
+/// # Returns
+///
+/// * `Option<u64>` - The current resident memory in bytes, None if the peak can't be reset
+#[cfg(target_os = "linux")]
+fn reset_peak_memory() -> Option<u64> {
+    // Writing 5 resets the high-water mark reported as VmHWM
+    fs::write("/proc/self/clear_refs", "5").ok()?;
+    peak_memory_bytes()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn reset_peak_memory() -> Option<u64> {
+    None
+}
+
+/// Peak resident memory of the process, in bytes.
+#[cfg(target_os = "linux")]
+fn peak_memory_bytes() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kilobytes: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+/// Peak resident memory of the process, in bytes.
+#[cfg(all(unix, not(target_os = "linux")))]
+fn peak_memory_bytes() -> Option<u64> {
+    // SAFETY: getrusage only writes to the struct it is given
+    let usage = unsafe {
+        let mut usage: libc::rusage = std::mem::zeroed();
+        if libc::getrusage(libc::RUSAGE_SELF, &mut usage) != 0 {
+            return None;
+        }
+        usage
+    };
+    let max_rss = u64::try_from(usage.ru_maxrss).ok()?;
+    // macOS reports bytes, the other systems kilobytes
+    if cfg!(target_os = "macos") {
+        Some(max_rss)
+    } else {
+        Some(max_rss * 1024)
+    }
+}
+
+/// Peak resident memory of the process (not measured on this platform).
+#[cfg(not(unix))]
+fn peak_memory_bytes() -> Option<u64> {
+    None
+}
+
+/// Nearest-rank percentile of the values, 0.0 for none.
+fn percentile(values: &[f32], percent: f32) -> f32 {
+    if values.is_empty() {
+        return 0.0;
+    }
+
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let rank = (percent / 100.0 * sorted.len() as f32).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn ratio(processing_seconds: f32, audio_seconds: f32) -> f32 {
+    if audio_seconds > 0.0 {
+        processing_seconds / audio_seconds
+    } else {
+        0.0
+    }
+}
+
+/// Quotes a CSV field if it contains a separator, quote or line break.
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles_use_the_nearest_rank() {
+        let values = [5.0, 1.0, 4.0, 2.0, 3.0];
+        assert_eq!(percentile(&values, 50.0), 3.0);
+        assert_eq!(percentile(&values, 90.0), 5.0);
+        assert_eq!(percentile(&values, 20.0), 1.0);
+        assert_eq!(percentile(&values, 0.0), 1.0);
+        assert_eq!(percentile(&[7.0], 99.0), 7.0);
+        assert_eq!(percentile(&[], 50.0), 0.0);
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_only_when_needed() {
+        assert_eq!(csv_field("plain text"), "plain text");
+        assert_eq!(csv_field("one, two"), "\"one, two\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn peak_memory_includes_released_allocations() {
+        let memory = MemorySampler::start();
+        let buffer = vec![1u8; 64 * 1024 * 1024];
+        assert_eq!(buffer.iter().map(|&b| b as usize).sum::<usize>(), buffer.len());
+        drop(buffer);
+        assert!(memory.used_bytes().unwrap() >= 32 * 1024 * 1024);
+    }
+}
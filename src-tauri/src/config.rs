@@ -1,6 +1,18 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+use crate::benchmark::{BenchmarkConfig, BenchmarkRun};
+use crate::evaluation::EvaluationConfig;
+use crate::transcription::batch::BatchConfig;
+use crate::transcription::engine::EngineKind;
+use crate::transcription::diarization::DiarizationConfig;
+use crate::transcription::filter::FilterConfig;
+use crate::transcription::normalize::NormalizationConfig;
+use crate::transcription::queue::{OverloadPolicy, QueueConfig};
+use crate::transcription::redaction::RedactionConfig;
+use crate::transcription::service::{TranscriptionConfig, TranscriptionTask};
+use crate::transcription::whisper::{self, DecodingConfig, DecodingStrategy};
 
 /// Configuration for audio recording parameters
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -15,6 +27,26 @@ pub struct AudioRecordingConfig {
     pub output_bits_per_sample: u16,
     /// Number of channels for the output WAV file (1 = mono, 2 = stereo)
     pub output_channels: u16,
+    /// Directory where per-session recordings are kept
+    #[serde(default = "default_recordings_dir")]
+    pub recordings_dir: String,
+    /// Retention policy applied to the recordings directory
+    #[serde(default)]
+    pub retention: RetentionConfig,
+}
+
+/// Retention policy for saved recordings
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RetentionConfig {
+    /// Maximum total size of all recordings in bytes (unlimited if absent)
+    #[serde(default)]
+    pub max_total_bytes: Option<u64>,
+    /// Maximum age of a recording in days (unlimited if absent)
+    #[serde(default)]
+    pub max_age_days: Option<u64>,
+    /// Whether to delete a recording once its transcript has been saved
+    #[serde(default)]
+    pub delete_after_transcription: bool,
 }
 
 /// Configuration for audio transcription
@@ -24,12 +56,392 @@ pub struct AudioTranscriptionConfig {
     pub whisper_sample_rate: u32,
     /// Minimum number of samples needed for transcription
     pub min_transcription_samples: usize,
-    /// Language to use for transcription
+    /// Language to use for transcription ("auto" to detect it)
     pub language: String,
     /// Minimum duration in seconds required for transcription
     pub min_duration_seconds: f32,
     /// Path to the Whisper model file
     pub path_to_model: String,
+    /// Directory scanned for models that can be switched to at runtime
+    #[serde(default = "default_models_dir")]
+    pub models_dir: String,
+    /// Whether to run a short inference after loading the model, to avoid first-call latency
+    #[serde(default = "default_warm_up")]
+    pub warm_up: bool,
+    /// Whether to transcribe or translate to English
+    #[serde(default)]
+    pub task: TranscriptionTask,
+    /// Context carry-over between consecutive windows
+    #[serde(default)]
+    pub context: TranscriptionContextConfig,
+    /// Custom vocabulary for product names and jargon
+    #[serde(default)]
+    pub vocabulary: VocabularySettings,
+    /// Whisper decoding parameters
+    #[serde(default)]
+    pub decoding: DecodingSettings,
+    /// Engine used for transcription: whisper or mock
+    #[serde(default)]
+    pub engine: EngineKind,
+    /// Script for the mock engine
+    #[serde(default)]
+    pub mock: MockEngineSettings,
+    /// Transcription of audio files
+    #[serde(default)]
+    pub batch: BatchSettings,
+    /// Filtering of hallucinations and artifacts in Whisper output
+    #[serde(default)]
+    pub filter: FilterSettings,
+    /// Rewriting of spoken numbers, dates, times and units into written form
+    #[serde(default)]
+    pub normalization: NormalizationSettings,
+    /// Masking of personal information in transcripts
+    #[serde(default)]
+    pub redaction: RedactionSettings,
+    /// Interim results for the window that is still being recorded
+    #[serde(default)]
+    pub interim: InterimSettings,
+    /// Labelling of segments with speakers
+    #[serde(default)]
+    pub diarization: DiarizationSettings,
+}
+
+/// Labelling of segments with speakers
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct DiarizationSettings {
+    /// Whether segments are labelled with speakers
+    pub enabled: bool,
+    /// Minimum cosine similarity (0.0 - 1.0) for a segment to join an existing speaker
+    pub similarity_threshold: f32,
+    /// Maximum number of speakers told apart in a live session
+    pub max_speakers: usize,
+    /// Number of speakers expected, if known: a file is split into exactly this
+    /// many, a live session tells at most this many apart
+    pub expected_speakers: Option<usize>,
+    /// Segments shorter than this (in seconds) take the speaker of the segment before them
+    pub min_segment_seconds: f32,
+}
+
+impl Default for DiarizationSettings {
+    fn default() -> Self {
+        let defaults = DiarizationConfig::default();
+        Self {
+            enabled: defaults.enabled,
+            similarity_threshold: defaults.similarity_threshold,
+            max_speakers: defaults.max_speakers,
+            expected_speakers: defaults.expected_speakers,
+            min_segment_seconds: defaults.min_segment_seconds,
+        }
+    }
+}
+
+impl DiarizationSettings {
+    /// Converts the settings into the configuration used for diarization.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_rate` - Sample rate of the audio passed to the transcription engine
+    pub fn to_diarization_config(&self, sample_rate: u32) -> DiarizationConfig {
+        DiarizationConfig {
+            enabled: self.enabled,
+            similarity_threshold: self.similarity_threshold,
+            max_speakers: self.max_speakers,
+            expected_speakers: self.expected_speakers,
+            min_segment_seconds: self.min_segment_seconds,
+            sample_rate,
+        }
+    }
+}
+
+/// Interim results for the window that is still being recorded
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct InterimSettings {
+    /// Whether to emit interim results before a window is complete
+    pub enabled: bool,
+    /// Seconds between interim transcriptions of the growing window
+    pub interval_seconds: f32,
+}
+
+impl Default for InterimSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_seconds: 1.0,
+        }
+    }
+}
+
+/// Filtering of hallucinations and artifacts in Whisper output
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct FilterSettings {
+    /// Whether the filter is applied at all
+    pub enabled: bool,
+    /// Whether to remove tags such as [BLANK_AUDIO], (music) or *laughs*
+    pub remove_non_speech_tags: bool,
+    /// Segments consisting only of one of these phrases are dropped
+    pub phrases: Vec<String>,
+    /// Phrases repeated back to back more often than this are collapsed (disabled if absent)
+    pub max_repetitions: Option<usize>,
+    /// Segments whose text compresses better than this ratio are dropped (disabled if absent)
+    pub max_compression_ratio: Option<f32>,
+    /// Segments above this no-speech probability and below logprob_threshold are dropped
+    /// (disabled if absent)
+    pub no_speech_threshold: Option<f32>,
+    /// Segments whose audio RMS level is below this and below logprob_threshold are
+    /// dropped, for engines that report no no-speech probability (disabled if absent)
+    pub silence_threshold: Option<f32>,
+    /// Average log-probability below which a likely no-speech segment is dropped
+    pub logprob_threshold: f32,
+}
+
+impl Default for FilterSettings {
+    fn default() -> Self {
+        let defaults = FilterConfig::default();
+        Self {
+            enabled: defaults.enabled,
+            remove_non_speech_tags: defaults.remove_non_speech_tags,
+            phrases: defaults.phrases,
+            max_repetitions: defaults.max_repetitions,
+            max_compression_ratio: defaults.max_compression_ratio,
+            no_speech_threshold: defaults.no_speech_threshold,
+            silence_threshold: defaults.silence_threshold,
+            logprob_threshold: defaults.logprob_threshold,
+        }
+    }
+}
+
+impl FilterSettings {
+    /// Converts the settings into the configuration used by the hallucination filter.
+    pub fn to_filter_config(&self) -> FilterConfig {
+        FilterConfig {
+            enabled: self.enabled,
+            remove_non_speech_tags: self.remove_non_speech_tags,
+            phrases: self.phrases.clone(),
+            max_repetitions: self.max_repetitions,
+            max_compression_ratio: self.max_compression_ratio,
+            no_speech_threshold: self.no_speech_threshold,
+            silence_threshold: self.silence_threshold,
+            logprob_threshold: self.logprob_threshold,
+        }
+    }
+}
+
+/// Rewriting of spoken forms in the transcript into written forms
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct NormalizationSettings {
+    /// Whether the text is normalized at all
+    pub enabled: bool,
+    /// Write spoken numbers as digits
+    pub numbers: bool,
+    /// Write dates in written form
+    pub dates: bool,
+    /// Write times of day in written form
+    pub times: bool,
+    /// Write amounts with currency symbols
+    pub currencies: bool,
+    /// Write units and percentages as symbols
+    pub units: bool,
+    /// Capitalize sentence starts and fix spacing around punctuation
+    pub capitalization: bool,
+    /// Keep the text as spoken next to the normalized text
+    pub keep_spoken_form: bool,
+}
+
+impl Default for NormalizationSettings {
+    fn default() -> Self {
+        let defaults = NormalizationConfig::default();
+        Self {
+            enabled: defaults.enabled,
+            numbers: defaults.numbers,
+            dates: defaults.dates,
+            times: defaults.times,
+            currencies: defaults.currencies,
+            units: defaults.units,
+            capitalization: defaults.capitalization,
+            keep_spoken_form: defaults.keep_spoken_form,
+        }
+    }
+}
+
+impl NormalizationSettings {
+    /// Converts the settings into the configuration used by the normalizer.
+    pub fn to_normalization_config(&self) -> NormalizationConfig {
+        NormalizationConfig {
+            enabled: self.enabled,
+            numbers: self.numbers,
+            dates: self.dates,
+            times: self.times,
+            currencies: self.currencies,
+            units: self.units,
+            capitalization: self.capitalization,
+            keep_spoken_form: self.keep_spoken_form,
+        }
+    }
+}
+
+/// Masking of personal information in transcripts
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct RedactionSettings {
+    /// Whether transcripts are redacted at all
+    pub enabled: bool,
+    /// Whether to redact email addresses
+    pub emails: bool,
+    /// Whether to redact phone numbers
+    pub phone_numbers: bool,
+    /// Whether to redact credit card numbers
+    pub credit_cards: bool,
+    /// Whether to redact IBANs
+    pub ibans: bool,
+    /// Additional terms to redact, e.g. names
+    pub terms: Vec<String>,
+}
+
+impl Default for RedactionSettings {
+    fn default() -> Self {
+        let defaults = RedactionConfig::default();
+        Self {
+            enabled: defaults.enabled,
+            emails: defaults.emails,
+            phone_numbers: defaults.phone_numbers,
+            credit_cards: defaults.credit_cards,
+            ibans: defaults.ibans,
+            terms: defaults.terms,
+        }
+    }
+}
+
+impl RedactionSettings {
+    /// Converts the settings into the configuration used by the redactor.
+    pub fn to_redaction_config(&self) -> RedactionConfig {
+        RedactionConfig {
+            enabled: self.enabled,
+            emails: self.emails,
+            phone_numbers: self.phone_numbers,
+            credit_cards: self.credit_cards,
+            ibans: self.ibans,
+            terms: self.terms.clone(),
+        }
+    }
+}
+
+/// Settings for transcribing audio files
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct BatchSettings {
+    /// Length of the windows a file is transcribed in, in seconds (Whisper handles at most 30)
+    pub window_seconds: f32,
+}
+
+impl Default for BatchSettings {
+    fn default() -> Self {
+        Self { window_seconds: 30.0 }
+    }
+}
+
+/// Script for the mock transcription engine
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct MockEngineSettings {
+    /// Canned responses; the n-th window gets the n-th response, cycling when exhausted
+    pub responses: Vec<String>,
+    /// Simulated inference time per window in milliseconds
+    pub latency_ms: u64,
+    /// Fail every n-th window (never fails if absent)
+    pub fail_every: Option<u32>,
+}
+
+/// Whisper decoding parameters
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct DecodingSettings {
+    /// Decoding strategy: greedy or beam_search
+    pub strategy: DecodingStrategy,
+    /// Number of candidates sampled by greedy decoding
+    pub best_of: u32,
+    /// Number of beams used by beam search
+    pub beam_size: u32,
+    /// Initial sampling temperature (0.0 = deterministic)
+    pub temperature: f32,
+    /// Temperature increase when decoding fails a quality check (0.0 disables fallback)
+    pub temperature_increment: f32,
+    /// Number of inference threads (number of cores, at most 4, if absent)
+    pub threads: Option<usize>,
+    /// Probability of the no-speech token above which a window is treated as silence
+    pub no_speech_threshold: f32,
+    /// Whether to suppress blank output at the start of a window
+    pub suppress_blank: bool,
+    /// Whether to suppress non-speech tokens such as music notes and brackets
+    pub suppress_non_speech_tokens: bool,
+    /// Maximum segment length in characters (0 = no limit)
+    pub max_segment_length: u32,
+}
+
+impl DecodingSettings {
+    /// Converts the settings into the decoding configuration used by the Whisper engine.
+    pub fn to_decoding_config(&self) -> DecodingConfig {
+        DecodingConfig {
+            strategy: self.strategy,
+            best_of: self.best_of,
+            beam_size: self.beam_size,
+            temperature: self.temperature,
+            temperature_increment: self.temperature_increment,
+            threads: self.threads,
+            no_speech_threshold: self.no_speech_threshold,
+            suppress_blank: self.suppress_blank,
+            suppress_non_speech_tokens: self.suppress_non_speech_tokens,
+            max_segment_length: self.max_segment_length,
+        }
+    }
+}
+
+impl Default for DecodingSettings {
+    fn default() -> Self {
+        let defaults = DecodingConfig::default();
+        Self {
+            strategy: defaults.strategy,
+            best_of: defaults.best_of,
+            beam_size: defaults.beam_size,
+            temperature: defaults.temperature,
+            temperature_increment: defaults.temperature_increment,
+            threads: defaults.threads,
+            no_speech_threshold: defaults.no_speech_threshold,
+            suppress_blank: defaults.suppress_blank,
+            suppress_non_speech_tokens: defaults.suppress_non_speech_tokens,
+            max_segment_length: defaults.max_segment_length,
+        }
+    }
+}
+
+/// Custom vocabulary injected into the prompt and used to correct the output
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VocabularySettings {
+    /// Terms to bias transcription towards
+    #[serde(default)]
+    pub terms: Vec<String>,
+    /// Optional file with one term per line; runtime edits are saved to it
+    #[serde(default)]
+    pub file: Option<String>,
+    /// Minimum similarity (0.0 - 1.0) for a transcribed phrase to be replaced by a term
+    /// (terms allow one edit per 7 characters; shorter terms need an exact match)
+    #[serde(default = "default_min_similarity")]
+    pub min_similarity: f32,
+}
+
+/// Configuration for carrying text over between consecutive transcription windows
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct TranscriptionContextConfig {
+    /// Whether previously transcribed text is used as the prompt for the next window
+    pub enabled: bool,
+    /// Maximum number of characters of previous text passed as the prompt
+    pub max_chars: usize,
+    /// Seconds without transcribed speech after which the context is dropped
+    pub reset_after_silence_seconds: f32,
 }
 
 /// Configuration for audio processing performance
@@ -37,6 +449,62 @@ pub struct AudioTranscriptionConfig {
 pub struct AudioPerformanceConfig {
     /// Buffer capacity for async channels
     pub channel_buffer_size: usize,
+    /// Number of audio windows that can wait for the transcription worker
+    #[serde(default = "default_transcription_queue_size")]
+    pub transcription_queue_size: usize,
+    /// Handling of windows when transcription can't keep up
+    #[serde(default)]
+    pub queue: QueueSettings,
+}
+
+/// Handling of windows when transcription can't keep up with the audio
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct QueueSettings {
+    /// What to do with windows past their deadline or beyond the queue size:
+    /// drop_oldest, merge or fallback_model
+    pub policy: OverloadPolicy,
+    /// Seconds a window may wait for the worker before the policy applies to it
+    pub deadline_seconds: f32,
+    /// Real-time factor above which the fallback model is loaded
+    pub max_real_time_factor: f32,
+    /// Faster model switched to under the fallback_model policy
+    pub fallback_model: Option<String>,
+    /// Longest window, in seconds, that merging may produce (Whisper handles at most 30)
+    pub max_merge_seconds: f32,
+}
+
+impl Default for QueueSettings {
+    fn default() -> Self {
+        let defaults = QueueConfig::default();
+        Self {
+            policy: defaults.policy,
+            deadline_seconds: defaults.deadline_seconds,
+            max_real_time_factor: defaults.max_real_time_factor,
+            fallback_model: defaults.fallback_model_path,
+            max_merge_seconds: defaults.max_merge_seconds,
+        }
+    }
+}
+
+impl QueueSettings {
+    /// Converts the settings into the configuration used by the transcription queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - Number of windows that can wait for the worker
+    /// * `sample_rate` - Sample rate of the audio passed to the transcription engine
+    pub fn to_queue_config(&self, capacity: usize, sample_rate: u32) -> QueueConfig {
+        QueueConfig {
+            capacity,
+            deadline_seconds: self.deadline_seconds,
+            policy: self.policy,
+            max_real_time_factor: self.max_real_time_factor,
+            fallback_model_path: self.fallback_model.clone(),
+            max_merge_seconds: self.max_merge_seconds,
+            sample_rate,
+        }
+    }
 }
 
 /// Combined audio configuration
@@ -59,6 +527,20 @@ pub struct CommandConfig {
     pub min_confidence: f32,
 }
 
+/// Encryption at rest for recordings and transcripts
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct EncryptionSettings {
+    /// Whether files should be encrypted before being written to disk
+    #[serde(default)]
+    pub enabled: bool,
+    /// Path to a keyfile containing a 32-byte key (raw or hex encoded)
+    #[serde(default)]
+    pub key_file: Option<String>,
+    /// Name of the environment variable holding the passphrase
+    #[serde(default)]
+    pub passphrase_env: Option<String>,
+}
+
 /// Top-level application configuration
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AppConfig {
@@ -67,6 +549,182 @@ pub struct AppConfig {
     /// Command detection configuration
     #[serde(default)]
     pub commands: CommandConfig,
+    /// Encryption at rest configuration
+    #[serde(default)]
+    pub encryption: EncryptionSettings,
+    /// Model and parameter sets compared by the benchmark command
+    #[serde(default)]
+    pub benchmark: BenchmarkSettings,
+    /// Reference transcripts scored by the evaluate command
+    #[serde(default)]
+    pub evaluation: EvaluationSettings,
+}
+
+/// Settings for the real-time factor benchmark (`app benchmark`)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct BenchmarkSettings {
+    /// Directory with the WAV files to transcribe
+    pub audio_dir: String,
+    /// Directory the JSON and CSV reports are written to
+    pub output_dir: String,
+    /// Length of the windows the audio is transcribed in, in seconds
+    /// (the live window length if absent)
+    pub window_seconds: Option<f32>,
+    /// Model and parameter sets to compare, each run over all files
+    pub runs: Vec<BenchmarkRunSettings>,
+}
+
+impl Default for BenchmarkSettings {
+    fn default() -> Self {
+        Self {
+            audio_dir: "benchmark/audio".to_string(),
+            output_dir: "benchmark/results".to_string(),
+            window_seconds: None,
+            runs: Vec::new(),
+        }
+    }
+}
+
+impl BenchmarkSettings {
+    /// Converts the settings into the benchmark configuration, deriving each run
+    /// from the configured transcription settings.
+    ///
+    /// # Arguments
+    ///
+    /// * `transcription` - Transcription configuration the runs override
+    /// * `live_window_seconds` - Window length used when none is configured
+    ///
+    /// # Returns
+    ///
+    /// * `BenchmarkConfig` - One run per configured run, or a single run of the
+    ///   configured model if none are configured
+    pub fn to_benchmark_config(&self, transcription: &TranscriptionConfig, live_window_seconds: f32) -> BenchmarkConfig {
+        let runs = if self.runs.is_empty() {
+            vec![BenchmarkRun {
+                name: "default".to_string(),
+                transcription: transcription.clone(),
+            }]
+        } else {
+            self.runs
+                .iter()
+                .map(|run| {
+                    let mut config = transcription.clone();
+                    if let Some(model) = &run.model {
+                        config.model_path = model.clone();
+                    }
+                    if let Some(decoding) = &run.decoding {
+                        config.decoding = decoding.to_decoding_config();
+                    }
+                    BenchmarkRun { name: run.name.clone(), transcription: config }
+                })
+                .collect()
+        };
+
+        BenchmarkConfig {
+            audio_dir: PathBuf::from(&self.audio_dir),
+            output_dir: PathBuf::from(&self.output_dir),
+            window_seconds: self.window_seconds.unwrap_or(live_window_seconds),
+            runs,
+        }
+    }
+}
+
+/// One model and parameter set of the benchmark
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BenchmarkRunSettings {
+    /// Name of the run in the report
+    pub name: String,
+    /// Path to the Whisper model file (the configured model if absent)
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Whisper decoding parameters (the configured ones if absent)
+    #[serde(default)]
+    pub decoding: Option<DecodingSettings>,
+}
+
+/// Settings for the accuracy evaluation (`app evaluate`)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct EvaluationSettings {
+    /// Directory with the audio files, each next to a `.txt` file with its reference transcript
+    pub audio_dir: String,
+    /// Directory the JSON and CSV reports are written to
+    pub output_dir: String,
+}
+
+impl Default for EvaluationSettings {
+    fn default() -> Self {
+        Self {
+            audio_dir: "evaluation/audio".to_string(),
+            output_dir: "evaluation/results".to_string(),
+        }
+    }
+}
+
+impl EvaluationSettings {
+    /// Converts the settings into the evaluation configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `transcription` - Transcription configuration to evaluate
+    /// * `window_seconds` - Window length of file transcription
+    ///
+    /// # Returns
+    ///
+    /// * `EvaluationConfig` - The configuration for the evaluation
+    pub fn to_evaluation_config(&self, transcription: &TranscriptionConfig, window_seconds: f32) -> EvaluationConfig {
+        EvaluationConfig {
+            audio_dir: PathBuf::from(&self.audio_dir),
+            output_dir: PathBuf::from(&self.output_dir),
+            transcription: transcription.clone(),
+            batch: BatchConfig {
+                window_seconds,
+                sample_rate: transcription.sample_rate as u32,
+                diarization: DiarizationConfig::default(),
+            },
+        }
+    }
+}
+
+fn default_recordings_dir() -> String {
+    "recordings".to_string()
+}
+
+fn default_models_dir() -> String {
+    "model".to_string()
+}
+
+fn default_warm_up() -> bool {
+    true
+}
+
+fn default_transcription_queue_size() -> usize {
+    4
+}
+
+fn default_min_similarity() -> f32 {
+    0.8
+}
+
+impl Default for VocabularySettings {
+    fn default() -> Self {
+        Self {
+            terms: Vec::new(),
+            file: None,
+            min_similarity: default_min_similarity(),
+        }
+    }
+}
+
+impl Default for TranscriptionContextConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_chars: 200,
+            reset_after_silence_seconds: 10.0,
+        }
+    }
 }
 
 impl Default for CommandConfig {
@@ -97,8 +755,56 @@ impl AppConfig {
         // Read and parse the file
         let config_content = fs::read_to_string(config_path)?;
         let config: AppConfig = serde_yaml::from_str(&config_content)?;
+        config.validate()?;
         Ok(config)
     }
+
+    /// Checks values that parse correctly but are outside their valid range.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - Ok if valid, Err describing the first invalid value
+    pub fn validate(&self) -> Result<(), String> {
+        let transcription = &self.audio.transcription;
+        if !whisper::is_supported_language(&transcription.language) {
+            return Err(format!("Unsupported transcription language \"{}\"", transcription.language));
+        }
+        if !(0.0..=1.0).contains(&transcription.vocabulary.min_similarity) {
+            return Err("vocabulary.min_similarity must be between 0.0 and 1.0".to_string());
+        }
+        if transcription.filter.no_speech_threshold.is_some_and(|t| !(0.0..=1.0).contains(&t)) {
+            return Err("filter.no_speech_threshold must be between 0.0 and 1.0".to_string());
+        }
+        if transcription.filter.silence_threshold.is_some_and(|t| !(0.0..=1.0).contains(&t)) {
+            return Err("filter.silence_threshold must be between 0.0 and 1.0".to_string());
+        }
+        if !(0.0..=1.0).contains(&transcription.diarization.similarity_threshold) {
+            return Err("diarization.similarity_threshold must be between 0.0 and 1.0".to_string());
+        }
+        if transcription.interim.interval_seconds <= 0.0 {
+            return Err("interim.interval_seconds must be greater than 0".to_string());
+        }
+        if !(1.0..=30.0).contains(&transcription.batch.window_seconds) {
+            return Err("batch.window_seconds must be between 1 and 30".to_string());
+        }
+        if self.benchmark.window_seconds.is_some_and(|w| !(1.0..=30.0).contains(&w)) {
+            return Err("benchmark.window_seconds must be between 1 and 30".to_string());
+        }
+        let queue = &self.audio.performance.queue;
+        if queue.deadline_seconds <= 0.0 {
+            return Err("queue.deadline_seconds must be greater than 0".to_string());
+        }
+        if queue.max_real_time_factor <= 0.0 {
+            return Err("queue.max_real_time_factor must be greater than 0".to_string());
+        }
+        if !(1.0..=30.0).contains(&queue.max_merge_seconds) {
+            return Err("queue.max_merge_seconds must be between 1 and 30".to_string());
+        }
+        if queue.policy == OverloadPolicy::FallbackModel && queue.fallback_model.is_none() {
+            return Err("queue.fallback_model is required for the fallback_model policy".to_string());
+        }
+        transcription.decoding.to_decoding_config().validate()
+    }
     
     /// Creates a default configuration with reasonable values.
     ///
@@ -114,6 +820,8 @@ impl AppConfig {
                     output_sample_rate: 44100,
                     output_bits_per_sample: 16,
                     output_channels: 1,
+                    recordings_dir: default_recordings_dir(),
+                    retention: RetentionConfig::default(),
                 },
                 transcription: AudioTranscriptionConfig {
                     whisper_sample_rate: 16000,
@@ -121,12 +829,45 @@ impl AppConfig {
                     language: "en".to_string(),
                     min_duration_seconds: 1.0,
                     path_to_model: "model/ggml-tiny.en.bin".to_string(),
+                    models_dir: default_models_dir(),
+                    warm_up: default_warm_up(),
+                    task: TranscriptionTask::Transcribe,
+                    context: TranscriptionContextConfig::default(),
+                    vocabulary: VocabularySettings::default(),
+                    decoding: DecodingSettings::default(),
+                    engine: EngineKind::Whisper,
+                    mock: MockEngineSettings::default(),
+                    batch: BatchSettings::default(),
+                    filter: FilterSettings::default(),
+                    normalization: NormalizationSettings::default(),
+                    redaction: RedactionSettings::default(),
+                    interim: InterimSettings::default(),
+                    diarization: DiarizationSettings::default(),
                 },
                 performance: AudioPerformanceConfig {
                     channel_buffer_size: 16,
+                    transcription_queue_size: default_transcription_queue_size(),
+                    queue: QueueSettings::default(),
                 },
             },
             commands: CommandConfig::default(),
+            encryption: EncryptionSettings::default(),
+            benchmark: BenchmarkSettings::default(),
+            evaluation: EvaluationSettings::default(),
         }
     }
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_context_settings_use_defaults() {
+        let context: TranscriptionContextConfig = serde_yaml::from_str("enabled: false").unwrap();
+        let defaults = TranscriptionContextConfig::default();
+        assert!(!context.enabled);
+        assert_eq!(context.max_chars, defaults.max_chars);
+        assert_eq!(context.reset_after_silence_seconds, defaults.reset_after_silence_seconds);
+    }
+}
@@ -0,0 +1,206 @@
+use std::fs;
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use sha2::Sha256;
+
+/// Marker at the start of every encrypted file
+const MAGIC: &[u8] = b"ACENC1";
+/// Length of the per-file salt used for passphrase key derivation
+const SALT_LEN: usize = 16;
+/// Length of the AES-GCM nonce
+const NONCE_LEN: usize = 12;
+/// Length of the AES-256 key
+const KEY_LEN: usize = 32;
+/// PBKDF2 iterations used to derive a key from a passphrase
+const PBKDF2_ROUNDS: u32 = 600_000;
+
+/// Extension appended to the name of encrypted files
+pub const ENCRYPTED_EXTENSION: &str = "enc";
+
+/// Configuration for encryption at rest
+#[derive(Clone, Default)]
+pub struct EncryptionConfig {
+    /// Whether files should be encrypted before being written to disk
+    pub enabled: bool,
+    /// Path to a keyfile containing a 32-byte key (raw or hex encoded)
+    pub key_file: Option<String>,
+    /// Name of the environment variable holding the passphrase
+    pub passphrase_env: Option<String>,
+}
+
+/// Secret the file keys are obtained from
+#[derive(Clone)]
+enum KeySource {
+    /// A fixed key read from a keyfile
+    Key([u8; KEY_LEN]),
+    /// A passphrase, stretched with a per-file salt
+    Passphrase(String),
+}
+
+/// Encryptor provides authenticated encryption (AES-256-GCM) for files at rest.
+///
+/// Encrypted files are laid out as `MAGIC | salt | nonce | ciphertext+tag`.
+#[derive(Clone)]
+pub struct Encryptor {
+    /// Secret used to obtain the file keys
+    source: KeySource,
+}
+
+impl Encryptor {
+    /// Creates an Encryptor from the configuration.
+    ///
+    /// A keyfile takes precedence over a passphrase when both are configured.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Encryption configuration
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<Self>, String>` - The encryptor, None if encryption is disabled
+    pub fn from_config(config: &EncryptionConfig) -> Result<Option<Self>, String> {
+        if !config.enabled {
+            return Ok(None);
+        }
+
+        if let Some(key_file) = &config.key_file {
+            let key = read_key_file(key_file)?;
+            return Ok(Some(Self { source: KeySource::Key(key) }));
+        }
+
+        if let Some(variable) = &config.passphrase_env {
+            let passphrase = std::env::var(variable)
+                .map_err(|_| format!("Passphrase environment variable {} is not set", variable))?;
+            if passphrase.is_empty() {
+                return Err(format!("Passphrase environment variable {} is empty", variable));
+            }
+            return Ok(Some(Self { source: KeySource::Passphrase(passphrase) }));
+        }
+
+        Err("Encryption is enabled but neither key_file nor passphrase_env is configured".to_string())
+    }
+
+    /// Encrypts the provided data.
+    ///
+    /// # Arguments
+    ///
+    /// * `plaintext` - Data to encrypt
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<u8>, String>` - The encrypted file contents
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, String> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+        let cipher = self.cipher(&salt);
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| "Failed to encrypt data".to_string())?;
+
+        let mut output = Vec::with_capacity(MAGIC.len() + SALT_LEN + NONCE_LEN + ciphertext.len());
+        output.extend_from_slice(MAGIC);
+        output.extend_from_slice(&salt);
+        output.extend_from_slice(&nonce);
+        output.extend_from_slice(&ciphertext);
+        Ok(output)
+    }
+
+    /// Decrypts data produced by [`Encryptor::encrypt`].
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - Encrypted file contents
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<u8>, String>` - The plaintext, or an error if the data was tampered
+    ///   with or the key is wrong
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        if !is_encrypted(data) || data.len() < MAGIC.len() + SALT_LEN + NONCE_LEN {
+            return Err("Data is not in the encrypted file format".to_string());
+        }
+
+        let (salt, rest) = data[MAGIC.len()..].split_at(SALT_LEN);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+        self.cipher(salt)
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| "Failed to decrypt data: wrong key or corrupted file".to_string())
+    }
+
+    /// Builds the cipher for a file with the given salt.
+    fn cipher(&self, salt: &[u8]) -> Aes256Gcm {
+        let key = match &self.source {
+            KeySource::Key(key) => *key,
+            KeySource::Passphrase(passphrase) => {
+                let mut key = [0u8; KEY_LEN];
+                pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, PBKDF2_ROUNDS, &mut key);
+                key
+            }
+        };
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+    }
+}
+
+/// Checks whether the data starts with the encrypted file marker.
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Reads a 32-byte key from a keyfile, either raw or as 64 hex characters.
+fn read_key_file(path: &str) -> Result<[u8; KEY_LEN], String> {
+    let contents = fs::read(path).map_err(|e| format!("Failed to read keyfile {}: {}", path, e))?;
+
+    if contents.len() == KEY_LEN {
+        let mut key = [0u8; KEY_LEN];
+        key.copy_from_slice(&contents);
+        return Ok(key);
+    }
+
+    let text = String::from_utf8_lossy(&contents);
+    let hex = text.trim();
+    if hex.len() != KEY_LEN * 2 || !hex.is_ascii() {
+        return Err(format!("Keyfile {} must contain 32 raw bytes or 64 hex characters", path));
+    }
+
+    let mut key = [0u8; KEY_LEN];
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+            .map_err(|_| format!("Keyfile {} contains invalid hex", path))?;
+    }
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_encryptor() -> Encryptor {
+        Encryptor { source: KeySource::Key([7; KEY_LEN]) }
+    }
+
+    #[test]
+    fn round_trip_with_key() {
+        let encryptor = key_encryptor();
+        let data = encryptor.encrypt(b"recorded audio").unwrap();
+        assert!(is_encrypted(&data));
+        assert_eq!(encryptor.decrypt(&data).unwrap(), b"recorded audio");
+    }
+
+    #[test]
+    fn tampered_or_foreign_data_is_rejected() {
+        let encryptor = key_encryptor();
+        let mut data = encryptor.encrypt(b"recorded audio").unwrap();
+        let last = data.len() - 1;
+        data[last] ^= 1;
+        assert!(encryptor.decrypt(&data).is_err());
+
+        let other = Encryptor { source: KeySource::Key([8; KEY_LEN]) };
+        assert!(other.decrypt(&key_encryptor().encrypt(b"x").unwrap()).is_err());
+        assert!(!is_encrypted(b"RIFF....WAVE"));
+    }
+}
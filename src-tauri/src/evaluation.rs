@@ -0,0 +1,535 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::benchmark::csv_field;
+use crate::transcription::batch::{self, BatchConfig};
+use crate::transcription::loader;
+use crate::transcription::normalize::{NormalizationConfig, Normalizer};
+use crate::transcription::service::{TranscriptionConfig, TranscriptionService, TranscriptionTask};
+
+/// Configuration for the accuracy evaluation
+#[derive(Clone)]
+pub struct EvaluationConfig {
+    /// Directory with the audio files, each next to a `.txt` file with its reference transcript
+    pub audio_dir: PathBuf,
+    /// Directory the reports are written to
+    pub output_dir: PathBuf,
+    /// Transcription configuration to evaluate
+    pub transcription: TranscriptionConfig,
+    /// Window settings, as for file transcription
+    pub batch: BatchConfig,
+}
+
+/// How a word of the hypothesis lines up with the reference
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EditKind {
+    /// The word was transcribed correctly
+    Correct,
+    /// A reference word was transcribed as a different word
+    Substitution,
+    /// The transcript has a word the reference doesn't
+    Insertion,
+    /// A reference word is missing from the transcript
+    Deletion,
+}
+
+/// One step of the alignment between reference and transcript
+#[derive(Debug, Clone, Serialize)]
+pub struct AlignedWord {
+    /// How the words line up
+    pub kind: EditKind,
+    /// Reference word, None for an insertion
+    pub reference: Option<String>,
+    /// Transcribed word, None for a deletion
+    pub hypothesis: Option<String>,
+}
+
+/// Edit counts of a transcript against its reference
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct ErrorCounts {
+    /// Number of words (or characters) in the reference
+    pub reference_length: usize,
+    /// Number of substituted words (or characters)
+    pub substitutions: usize,
+    /// Number of inserted words (or characters)
+    pub insertions: usize,
+    /// Number of deleted words (or characters)
+    pub deletions: usize,
+    /// Errors divided by the reference length; may exceed 1.0 with many insertions
+    pub error_rate: f32,
+}
+
+impl ErrorCounts {
+    fn new(reference_length: usize, substitutions: usize, insertions: usize, deletions: usize) -> Self {
+        let errors = substitutions + insertions + deletions;
+        let error_rate = if reference_length > 0 {
+            errors as f32 / reference_length as f32
+        } else if errors > 0 {
+            1.0
+        } else {
+            0.0
+        };
+        Self { reference_length, substitutions, insertions, deletions, error_rate }
+    }
+
+    /// Sums the counts, weighting each error rate by its reference length.
+    fn sum<'a>(counts: impl Iterator<Item = &'a ErrorCounts>) -> Self {
+        let (length, s, i, d) = counts.fold((0, 0, 0, 0), |(length, s, i, d), c| {
+            (length + c.reference_length, s + c.substitutions, i + c.insertions, d + c.deletions)
+        });
+        Self::new(length, s, i, d)
+    }
+}
+
+/// Result of evaluating one file
+#[derive(Debug, Clone, Serialize)]
+pub struct FileEvaluation {
+    /// File that was transcribed
+    pub file: String,
+    /// Reference transcript as scored, after normalization
+    pub reference: String,
+    /// Transcript as scored, after normalization
+    pub hypothesis: String,
+    /// Word error counts (WER)
+    pub words: ErrorCounts,
+    /// Character error counts (CER), spaces included
+    pub characters: ErrorCounts,
+    /// Word-by-word alignment of the transcript with the reference
+    pub alignment: Vec<AlignedWord>,
+    /// Why the file failed to transcribe; its whole reference then counts as deleted
+    pub error: Option<String>,
+}
+
+/// Report of an evaluation
+#[derive(Debug, Clone, Serialize)]
+pub struct EvaluationReport {
+    /// Unix time the evaluation started at
+    pub started_at: u64,
+    /// Model that was evaluated
+    pub model: String,
+    /// Word error counts over all files
+    pub words: ErrorCounts,
+    /// Character error counts over all files
+    pub characters: ErrorCounts,
+    /// Result of each file, including the ones that failed to transcribe
+    pub files: Vec<FileEvaluation>,
+    /// Number of files that failed to transcribe
+    pub failed: usize,
+}
+
+/// Transcribes every audio file with a reference transcript and scores the result.
+///
+/// Files go through the same decoding, resampling and transcription pipeline as
+/// file transcription. Both transcript and reference are normalized before
+/// scoring, so "twenty five" and "25" or differences in case and punctuation
+/// don't count as errors. A file that fails to transcribe is scored as an empty
+/// transcript, so a failure never makes the error rates look better.
+///
+/// # Arguments
+///
+/// * `config` - Audio directory and the transcription configuration to evaluate
+///
+/// # Returns
+///
+/// * `Result<EvaluationReport, String>` - The report, or an error message if no files
+///   can be read or the model fails to load
+pub fn run(config: &EvaluationConfig) -> Result<EvaluationReport, String> {
+    let started_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let files = find_files(&config.audio_dir)?;
+    println!("Evaluating {} files", files.len());
+
+    let mut transcription = config.transcription.clone();
+    // Placeholders would count as errors against the reference
+    transcription.redaction.enabled = false;
+    let model = transcription.model_path.clone();
+    let service = Mutex::new(TranscriptionService::with_config(transcription));
+    service.lock().unwrap().prepare()?;
+    loader::load_model(&service, None, &|_| {})?;
+
+    // Both sides are scored in written form, whatever the app's setting
+    let normalizer = Normalizer::with_config(NormalizationConfig { enabled: true, ..NormalizationConfig::default() });
+    let cancel = Mutex::new(false);
+    let mut results = Vec::new();
+    for (audio_path, reference_path) in files {
+        let reference = fs::read_to_string(&reference_path)
+            .map_err(|e| format!("Failed to read {}: {}", reference_path.display(), e))?;
+        let transcript = match batch::transcribe_file(&audio_path, None, &service, &config.batch, &cancel, &|_| {}) {
+            Ok(transcript) => transcript,
+            Err(e) => {
+                println!("Failed to transcribe {}: {}", audio_path.display(), e);
+                let language = &config.transcription.language;
+                let mut result = evaluate_file(
+                    &file_name(&audio_path),
+                    &scoring_words(&normalizer, &reference, language),
+                    &[],
+                );
+                result.error = Some(e);
+                results.push(result);
+                continue;
+            }
+        };
+
+        // Score in the language that was transcribed
+        let language = match transcript.segments.first() {
+            Some(segment) if segment.task == TranscriptionTask::Translate => "en".to_string(),
+            Some(segment) => segment.language.clone(),
+            None => config.transcription.language.clone(),
+        };
+        let hypothesis: String = transcript.segments.iter().map(|segment| segment.text.as_str()).collect();
+
+        let result = evaluate_file(
+            &file_name(&audio_path),
+            &scoring_words(&normalizer, &reference, &language),
+            &scoring_words(&normalizer, &hypothesis, &language),
+        );
+        println!(
+            "{}: WER {:.1}%, CER {:.1}%",
+            result.file,
+            result.words.error_rate * 100.0,
+            result.characters.error_rate * 100.0
+        );
+        results.push(result);
+    }
+    let failed = results.iter().filter(|r| r.error.is_some()).count();
+    if failed == results.len() {
+        return Err("No file could be transcribed".to_string());
+    }
+
+    Ok(EvaluationReport {
+        started_at,
+        model,
+        words: ErrorCounts::sum(results.iter().map(|r| &r.words)),
+        characters: ErrorCounts::sum(results.iter().map(|r| &r.characters)),
+        files: results,
+        failed,
+    })
+}
+
+/// Scores a transcript against its reference, both already split into scoring words.
+fn evaluate_file(file: &str, reference: &[String], hypothesis: &[String]) -> FileEvaluation {
+    let alignment: Vec<AlignedWord> = align(reference, hypothesis)
+        .into_iter()
+        .map(|(kind, r, h)| AlignedWord {
+            kind,
+            reference: r.map(|index| reference[index].clone()),
+            hypothesis: h.map(|index| hypothesis[index].clone()),
+        })
+        .collect();
+    let count = |kind: EditKind| alignment.iter().filter(|word| word.kind == kind).count();
+    let words = ErrorCounts::new(
+        reference.len(),
+        count(EditKind::Substitution),
+        count(EditKind::Insertion),
+        count(EditKind::Deletion),
+    );
+
+    let reference = reference.join(" ");
+    let hypothesis = hypothesis.join(" ");
+    let reference_chars: Vec<char> = reference.chars().collect();
+    let hypothesis_chars: Vec<char> = hypothesis.chars().collect();
+    let characters = count_edits(&reference_chars, &hypothesis_chars);
+
+    FileEvaluation {
+        file: file.to_string(),
+        reference,
+        hypothesis,
+        words,
+        characters,
+        alignment,
+        error: None,
+    }
+}
+
+/// Normalizes text for scoring and splits it into lowercase words without
+/// surrounding punctuation.
+fn scoring_words(normalizer: &Normalizer, text: &str, language: &str) -> Vec<String> {
+    normalizer
+        .normalize(text, language)
+        .to_lowercase()
+        .split_whitespace()
+        .map(|word| {
+            // Symbols that carry meaning, as in "$5" or "10%", are kept
+            word.trim_matches(|c: char| c.is_ascii_punctuation() && !matches!(c, '$' | '%'))
+                .trim_matches(|c: char| matches!(c, '“' | '”' | '‘' | '’' | '„' | '«' | '»' | '…'))
+                .to_string()
+        })
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// Aligns a hypothesis with a reference by minimum edit distance.
+///
+/// On ties, matches and substitutions are preferred over deletions, and
+/// deletions over insertions.
+///
+/// # Returns
+///
+/// * `Vec<(EditKind, Option<usize>, Option<usize>)>` - Each step with the indices of
+///   the reference and hypothesis items involved, in order
+fn align<T: PartialEq>(reference: &[T], hypothesis: &[T]) -> Vec<(EditKind, Option<usize>, Option<usize>)> {
+    let columns = hypothesis.len() + 1;
+    // Costs are kept for two rows; the steps for the whole table, to walk it back
+    let mut steps = vec![EditKind::Correct; (reference.len() + 1) * columns];
+    let mut previous: Vec<usize> = (0..columns).collect();
+    let mut current = vec![0; columns];
+    steps[1..columns].fill(EditKind::Insertion);
+    for row in 1..=reference.len() {
+        current[0] = row;
+        steps[row * columns] = EditKind::Deletion;
+        for column in 1..columns {
+            let matches = reference[row - 1] == hypothesis[column - 1];
+            let diagonal = previous[column - 1] + usize::from(!matches);
+            let deletion = previous[column] + 1;
+            let insertion = current[column - 1] + 1;
+            let (cost, kind) = if diagonal <= deletion && diagonal <= insertion {
+                (diagonal, if matches { EditKind::Correct } else { EditKind::Substitution })
+            } else if deletion <= insertion {
+                (deletion, EditKind::Deletion)
+            } else {
+                (insertion, EditKind::Insertion)
+            };
+            current[column] = cost;
+            steps[row * columns + column] = kind;
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    let mut alignment = Vec::new();
+    let (mut row, mut column) = (reference.len(), hypothesis.len());
+    while row > 0 || column > 0 {
+        let kind = steps[row * columns + column];
+        match kind {
+            EditKind::Correct | EditKind::Substitution => {
+                row -= 1;
+                column -= 1;
+                alignment.push((kind, Some(row), Some(column)));
+            }
+            EditKind::Deletion => {
+                row -= 1;
+                alignment.push((kind, Some(row), None));
+            }
+            EditKind::Insertion => {
+                column -= 1;
+                alignment.push((kind, None, Some(column)));
+            }
+        }
+    }
+    alignment.reverse();
+    alignment
+}
+
+/// Counts the edits between a hypothesis and a reference without keeping the
+/// alignment, so long character sequences need only two rows of memory. Ties are
+/// broken like in [`align`].
+fn count_edits<T: PartialEq>(reference: &[T], hypothesis: &[T]) -> ErrorCounts {
+    // Each cell holds (cost, substitutions, insertions, deletions)
+    let mut previous: Vec<(usize, usize, usize, usize)> = (0..=hypothesis.len()).map(|i| (i, 0, i, 0)).collect();
+    let mut current = previous.clone();
+    for row in 1..=reference.len() {
+        current[0] = (row, 0, 0, row);
+        for column in 1..=hypothesis.len() {
+            let matches = reference[row - 1] == hypothesis[column - 1];
+            let (cost, s, i, d) = previous[column - 1];
+            let diagonal = if matches { (cost, s, i, d) } else { (cost + 1, s + 1, i, d) };
+            let (cost, s, i, d) = previous[column];
+            let deletion = (cost + 1, s, i, d + 1);
+            let (cost, s, i, d) = current[column - 1];
+            let insertion = (cost + 1, s, i + 1, d);
+            current[column] = if diagonal.0 <= deletion.0 && diagonal.0 <= insertion.0 {
+                diagonal
+            } else if deletion.0 <= insertion.0 {
+                deletion
+            } else {
+                insertion
+            };
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    let (_, substitutions, insertions, deletions) = previous[hypothesis.len()];
+    ErrorCounts::new(reference.len(), substitutions, insertions, deletions)
+}
+
+/// Finds the audio files in a directory that have a reference transcript, in name order.
+///
+/// The reference of `name.wav` (or any other audio or video file) is `name.txt`.
+fn find_files(dir: &Path) -> Result<Vec<(PathBuf, PathBuf)>, String> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read evaluation directory {}: {}", dir.display(), e))?;
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.is_file()
+                && !path
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("txt"))
+        })
+        .collect();
+    paths.sort();
+
+    let mut files = Vec::new();
+    for path in paths {
+        let reference = path.with_extension("txt");
+        if reference.is_file() {
+            files.push((path, reference));
+        } else {
+            println!("Skipping {}: no reference transcript {}", path.display(), reference.display());
+        }
+    }
+    if files.is_empty() {
+        return Err(format!("No audio files with reference transcripts in {}", dir.display()));
+    }
+    Ok(files)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Writes the report as JSON, with the alignment of every file, and as CSV with
+/// one row per file followed by a row with the totals and the file column set to `*`.
+/// Files that failed to transcribe have their error in the last column.
+///
+/// # Arguments
+///
+/// * `report` - The report to write
+/// * `dir` - Directory to write `evaluation-<time>.json` and `.csv` to
+///
+/// # Returns
+///
+/// * `Result<(PathBuf, PathBuf), String>` - Paths of the JSON and CSV files
+pub fn write_report(report: &EvaluationReport, dir: &Path) -> Result<(PathBuf, PathBuf), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let stem = dir.join(format!("evaluation-{}", report.started_at));
+
+    let json_path = stem.with_extension("json");
+    let json = serde_json::to_string_pretty(report).map_err(|e| format!("Failed to serialize report: {}", e))?;
+    fs::write(&json_path, json).map_err(|e| format!("Failed to write {}: {}", json_path.display(), e))?;
+
+    let mut csv = String::from(
+        "file,reference_words,substitutions,insertions,deletions,wer,reference_characters,character_substitutions,character_insertions,character_deletions,cer,reference,hypothesis,error\n",
+    );
+    let row = |file: &str, words: &ErrorCounts, characters: &ErrorCounts, reference: &str, hypothesis: &str, error: &str| {
+        format!(
+            "{},{},{},{},{},{:.4},{},{},{},{},{:.4},{},{},{}\n",
+            csv_field(file),
+            words.reference_length,
+            words.substitutions,
+            words.insertions,
+            words.deletions,
+            words.error_rate,
+            characters.reference_length,
+            characters.substitutions,
+            characters.insertions,
+            characters.deletions,
+            characters.error_rate,
+            csv_field(reference),
+            csv_field(hypothesis),
+            csv_field(error),
+        )
+    };
+    for file in &report.files {
+        let error = file.error.as_deref().unwrap_or_default();
+        csv.push_str(&row(&file.file, &file.words, &file.characters, &file.reference, &file.hypothesis, error));
+    }
+    csv.push_str(&row("*", &report.words, &report.characters, "", "", ""));
+    let csv_path = stem.with_extension("csv");
+    fs::write(&csv_path, csv).map_err(|e| format!("Failed to write {}: {}", csv_path.display(), e))?;
+
+    Ok((json_path, csv_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(text: &str) -> Vec<String> {
+        text.split_whitespace().map(str::to_string).collect()
+    }
+
+    /// Substitutions, insertions and deletions of the word alignment.
+    fn edits(reference: &str, hypothesis: &str) -> (usize, usize, usize) {
+        let evaluation = evaluate_file("test", &words(reference), &words(hypothesis));
+        (evaluation.words.substitutions, evaluation.words.insertions, evaluation.words.deletions)
+    }
+
+    #[test]
+    fn alignment_counts_each_kind_of_error() {
+        assert_eq!(edits("the cat sat", "the cat sat"), (0, 0, 0));
+        assert_eq!(edits("the cat sat", "the bat sat"), (1, 0, 0));
+        assert_eq!(edits("the cat sat", "the cat sat down"), (0, 1, 0));
+        assert_eq!(edits("the cat sat", "the sat"), (0, 0, 1));
+        assert_eq!(edits("a b c d", "a x c d e"), (1, 1, 0));
+        assert_eq!(edits("", "so"), (0, 1, 0));
+        assert_eq!(edits("so", ""), (0, 0, 1));
+    }
+
+    #[test]
+    fn ties_prefer_substitutions() {
+        // Two substitutions and an insertion cost as much as two insertions and a deletion
+        assert_eq!(edits("a b c", "x a c d"), (2, 1, 0));
+    }
+
+    #[test]
+    fn alignment_steps_point_at_the_aligned_words() {
+        let alignment = align(&words("the cat sat"), &words("cat sat down"));
+        let kinds: Vec<EditKind> = alignment.iter().map(|(kind, _, _)| *kind).collect();
+        assert_eq!(kinds, [EditKind::Deletion, EditKind::Correct, EditKind::Correct, EditKind::Insertion]);
+        assert_eq!(alignment[0], (EditKind::Deletion, Some(0), None));
+        assert_eq!(alignment[1], (EditKind::Correct, Some(1), Some(0)));
+        assert_eq!(alignment[3], (EditKind::Insertion, None, Some(2)));
+    }
+
+    #[test]
+    fn counting_edits_matches_the_alignment() {
+        let pairs = [("kitten", "sitting"), ("abc", "xacd"), ("flaw", "lawn"), ("", "abc"), ("abc", "")];
+        for (reference, hypothesis) in pairs {
+            let reference: Vec<char> = reference.chars().collect();
+            let hypothesis: Vec<char> = hypothesis.chars().collect();
+            let alignment = align(&reference, &hypothesis);
+            let count = |kind: EditKind| alignment.iter().filter(|(k, _, _)| *k == kind).count();
+
+            let counts = count_edits(&reference, &hypothesis);
+            assert_eq!(
+                (counts.substitutions, counts.insertions, counts.deletions),
+                (count(EditKind::Substitution), count(EditKind::Insertion), count(EditKind::Deletion)),
+            );
+        }
+    }
+
+    #[test]
+    fn error_rates_are_relative_to_the_reference() {
+        let evaluation = evaluate_file("test", &words("one two three four"), &words("one too three"));
+        assert_eq!(evaluation.words.error_rate, 0.5);
+        assert_eq!(ErrorCounts::new(0, 0, 2, 0).error_rate, 1.0);
+        assert_eq!(ErrorCounts::new(0, 0, 0, 0).error_rate, 0.0);
+
+        let total = ErrorCounts::sum([ErrorCounts::new(4, 1, 0, 0), ErrorCounts::new(6, 0, 1, 1)].iter());
+        assert_eq!(total.error_rate, 0.3);
+    }
+
+    #[test]
+    fn an_empty_transcript_deletes_the_whole_reference() {
+        let evaluation = evaluate_file("test", &words("one two three"), &[]);
+        assert_eq!((evaluation.words.deletions, evaluation.words.error_rate), (3, 1.0));
+        assert_eq!(evaluation.characters.error_rate, 1.0);
+    }
+
+    #[test]
+    fn scoring_ignores_case_and_punctuation_but_keeps_symbols() {
+        let normalizer = Normalizer::with_config(NormalizationConfig { enabled: true, ..NormalizationConfig::default() });
+        assert_eq!(scoring_words(&normalizer, "Hello, World! It's “fine”.", "en"), ["hello", "world", "it's", "fine"]);
+        assert_eq!(scoring_words(&normalizer, "ten percent", "en"), ["10%"]);
+    }
+}
@@ -2,6 +2,7 @@
 use std::sync::Mutex;
 use std::sync::Arc;
 use std::sync::mpsc;
+use std::path::Path;
 use tauri::{AppHandle, Emitter};
 
 mod audio;
@@ -9,36 +10,70 @@ mod config;
 mod transcription;
 mod orchestrator;
 mod command;
+mod encryption;
+mod benchmark;
+mod evaluation;
 
 use config::AppConfig;
 use orchestrator::Orchestrator;
 use audio::recorder::Recorder;
 use audio::processor::AudioProcessor;
 use audio::storage::AudioStorage;
-use transcription::service::TranscriptionService;
+use audio::decoder::{self, AudioTrackInfo};
+use transcription::segment::TranscriptSegment;
+use transcription::service::{ContextConfig, TranscriptionService, TranscriptionTask};
 use command::detector::CommandDetector;
+use transcription::mock::MockEngineConfig;
+use transcription::vocabulary::VocabularyConfig;
+use transcription::models::ModelInfo;
+use transcription::batch::FileTranscript;
+use transcription::loader::{self, ModelState, StatusReporter};
+use transcription::queue::QueueDecision;
+use encryption::{EncryptionConfig, Encryptor};
 
 // Import the specific configuration structs
 use audio::recorder::RecorderConfig;
 use audio::processor::ProcessorConfig;
-use audio::storage::StorageConfig;
+use audio::storage::{RetentionAction, RetentionPolicy, StorageConfig};
 use transcription::service::TranscriptionConfig;
 use command::detector::CommandDetectorConfig;
 
 #[tauri::command]
-fn start_recording(app: AppHandle, orchestrator: tauri::State<Arc<Mutex<Orchestrator>>>) {
+async fn start_recording(
+    app: AppHandle,
+    orchestrator: tauri::State<'_, Arc<Mutex<Orchestrator>>>,
+) -> Result<(), String> {
     // Clone the Arc from the state
     let orchestrator_arc = orchestrator.inner().clone();
-    let (sender_channel, receiver_channel) = mpsc::channel::<String>();
+    let (sender_channel, receiver_channel) = mpsc::channel::<TranscriptSegment>();
+    let (decision_sender, decision_receiver) = mpsc::channel::<QueueDecision>();
 
-    // Spawn an async task that starts the orchestrator
+    // A model switched to during the session reports like one switched to by the user
+    let status_app = app.clone();
+    let model_status: StatusReporter = Arc::new(move |state| emit_model_status(&status_app, state));
+
+    // A stopped session may still be transcribing its last windows; wait until it is
+    // saved, since the next session reuses its buffers
+    let stopped_session = orchestrator_arc.lock().unwrap().take_stopped_session();
+    if let Some(handle) = stopped_session {
+        if let Err(e) = handle.await {
+            println!("Previous session failed: {:?}", e);
+        }
+    }
+
+    // Spawn an async task that starts the orchestrator; fails while a file is being transcribed
     tauri::async_runtime::spawn(async move {
         let mut orchestrator = orchestrator_arc.lock().unwrap();
-        orchestrator.start(sender_channel);
-    });
+        orchestrator.start(sender_channel, decision_sender, model_status)
+    })
+    .await
+    .map_err(|e| format!("Recording task failed: {:?}", e))??;
 
-    // Spawn the async task that sends transcription chunks back
-    tauri::async_runtime::spawn(send_transcribe_chunks_back(app, receiver_channel));
+    // Forward transcription chunks and queue decisions on blocking threads, since the receivers block
+    let decision_app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || send_transcribe_chunks_back(app, receiver_channel));
+    tauri::async_runtime::spawn_blocking(move || send_queue_decisions_back(decision_app, decision_receiver));
+    Ok(())
 }
 
 #[tauri::command]
@@ -53,7 +88,129 @@ fn stop_recording(orchestrator: tauri::State<Arc<Mutex<Orchestrator>>>) {
     });
 }
 
-async fn send_transcribe_chunks_back(app: AppHandle, receiver_channel: mpsc::Receiver<String>) {
+#[tauri::command]
+fn set_transcription_task(task: TranscriptionTask, orchestrator: tauri::State<Arc<Mutex<Orchestrator>>>) {
+    // Takes effect from the next transcribed window
+    orchestrator.lock().unwrap().set_transcription_task(task);
+}
+
+#[tauri::command]
+fn get_vocabulary(orchestrator: tauri::State<Arc<Mutex<Orchestrator>>>) -> Vec<String> {
+    orchestrator.lock().unwrap().vocabulary()
+}
+
+#[tauri::command]
+fn set_vocabulary(terms: Vec<String>, orchestrator: tauri::State<Arc<Mutex<Orchestrator>>>) -> Result<(), String> {
+    // Takes effect from the next transcribed window
+    orchestrator.lock().unwrap().set_vocabulary(terms)
+}
+
+#[tauri::command]
+fn list_models(orchestrator: tauri::State<Arc<Mutex<Orchestrator>>>) -> Result<Vec<ModelInfo>, String> {
+    orchestrator.lock().unwrap().list_models()
+}
+
+#[tauri::command]
+async fn switch_model(
+    path: String,
+    app: AppHandle,
+    orchestrator: tauri::State<'_, Arc<Mutex<Orchestrator>>>,
+) -> Result<ModelInfo, String> {
+    // Only models listed from the models directory can be loaded, not arbitrary files
+    let (available, service) = {
+        let orchestrator = orchestrator.lock().unwrap();
+        (orchestrator.list_models()?, orchestrator.transcription_service())
+    };
+    let requested = std::fs::canonicalize(&path).map_err(|e| format!("Failed to resolve {}: {}", path, e))?;
+    let info = available
+        .into_iter()
+        .find(|model| std::fs::canonicalize(&model.path).is_ok_and(|listed| listed == requested))
+        .ok_or_else(|| format!("{} is not a model in the models directory", path))?;
+    let model_path = info.path.to_string_lossy().to_string();
+
+    // Load in the background; transcription keeps using the current model meanwhile
+    // and the swap lands between windows
+    tauri::async_runtime::spawn_blocking(move || {
+        loader::load_model(&service, Some(model_path), &|state| emit_model_status(&app, state))
+    })
+    .await
+    .map_err(|e| format!("Model loading task failed: {:?}", e))??;
+    Ok(info)
+}
+
+#[tauri::command]
+fn get_model_status(orchestrator: tauri::State<Arc<Mutex<Orchestrator>>>) -> ModelState {
+    orchestrator.lock().unwrap().model_state()
+}
+
+#[tauri::command]
+async fn transcribe_file(
+    path: String,
+    track: Option<usize>,
+    diarize: Option<bool>,
+    speakers: Option<usize>,
+    app: AppHandle,
+    orchestrator: tauri::State<'_, Arc<Mutex<Orchestrator>>>,
+) -> Result<FileTranscript, String> {
+    let mut job = orchestrator.lock().unwrap().file_transcription()?;
+
+    // Both override the configuration for this file only; the number of speakers
+    // has no effect unless diarization is enabled
+    if let Some(diarize) = diarize {
+        job.config.diarization.enabled = diarize;
+    }
+    if speakers.is_some() {
+        job.config.diarization.expected_speakers = speakers;
+    }
+
+    // Inference is CPU-bound, keep it off the async runtime
+    tauri::async_runtime::spawn_blocking(move || {
+        job.run(Path::new(&path), track, &|progress| {
+            if let Err(err) = app.emit("transcribe_file_progress", progress) {
+                eprintln!("Failed to emit file progress event: {:?}", err);
+            }
+        })
+    })
+    .await
+    .map_err(|e| format!("File transcription task failed: {:?}", e))?
+}
+
+#[tauri::command]
+fn list_audio_tracks(path: String) -> Result<Vec<AudioTrackInfo>, String> {
+    // Lets the caller pick the track of a multi-track recording or video
+    decoder::audio_tracks(Path::new(&path))
+}
+
+#[tauri::command]
+fn cancel_file_transcription(orchestrator: tauri::State<Arc<Mutex<Orchestrator>>>) {
+    // Takes effect after the window currently being transcribed
+    orchestrator.lock().unwrap().cancel_file_transcription();
+}
+
+#[tauri::command]
+fn preview_retention(orchestrator: tauri::State<Arc<Mutex<Orchestrator>>>) -> Result<Vec<RetentionAction>, String> {
+    // Dry run: list what the retention policy would delete right now
+    orchestrator.lock().unwrap().preview_retention()
+}
+
+#[tauri::command]
+fn export_file(
+    source: String,
+    destination: String,
+    orchestrator: tauri::State<Arc<Mutex<Orchestrator>>>,
+) -> Result<(), String> {
+    // Decrypt (if needed) a stored recording or transcript to a plaintext copy
+    orchestrator.lock().unwrap().export_file(Path::new(&source), Path::new(&destination))
+}
+
+fn emit_model_status(app: &AppHandle, state: &ModelState) {
+    if let Err(err) = app.emit("model_status", state) {
+        eprintln!("Failed to emit model status event: {:?}", err);
+    }
+}
+
+fn send_transcribe_chunks_back(app: AppHandle, receiver_channel: mpsc::Receiver<TranscriptSegment>) {
+    // Segments are serialized to JSON by Tauri
     while let Ok(data) = receiver_channel.recv() {
         if let Err(err) = app.emit("transcribe", data) {
             eprintln!("Failed to emit transcription event: {:?}", err);
@@ -61,14 +218,168 @@ async fn send_transcribe_chunks_back(app: AppHandle, receiver_channel: mpsc::Rec
     }
 }
 
+fn send_queue_decisions_back(app: AppHandle, receiver_channel: mpsc::Receiver<QueueDecision>) {
+    // Ends with the session, when the worker drops its queue
+    while let Ok(decision) = receiver_channel.recv() {
+        if let Err(err) = app.emit("queue_decision", decision) {
+            eprintln!("Failed to emit queue decision event: {:?}", err);
+        }
+    }
+}
+
+/// Builds the transcription configuration from the application configuration.
+fn transcription_config(app_config: &AppConfig) -> TranscriptionConfig {
+    TranscriptionConfig {
+        language: app_config.audio.transcription.language.clone(),
+        min_duration_seconds: app_config.audio.transcription.min_duration_seconds,
+        sample_rate: app_config.audio.transcription.whisper_sample_rate as usize,
+        model_path: app_config.audio.transcription.path_to_model.clone(),
+        task: app_config.audio.transcription.task,
+        context: ContextConfig {
+            enabled: app_config.audio.transcription.context.enabled,
+            max_chars: app_config.audio.transcription.context.max_chars,
+            reset_after_silence_seconds: app_config.audio.transcription.context.reset_after_silence_seconds,
+        },
+        vocabulary: VocabularyConfig {
+            terms: app_config.audio.transcription.vocabulary.terms.clone(),
+            file: app_config.audio.transcription.vocabulary.file.clone(),
+            min_similarity: app_config.audio.transcription.vocabulary.min_similarity,
+        },
+        decoding: app_config.audio.transcription.decoding.to_decoding_config(),
+        engine: app_config.audio.transcription.engine,
+        mock: MockEngineConfig {
+            responses_by_input: Default::default(),
+            responses: app_config.audio.transcription.mock.responses.clone(),
+            latency_ms: app_config.audio.transcription.mock.latency_ms,
+            fail_every: app_config.audio.transcription.mock.fail_every,
+        },
+        warm_up: app_config.audio.transcription.warm_up,
+        filter: app_config.audio.transcription.filter.to_filter_config(),
+        normalization: app_config.audio.transcription.normalization.to_normalization_config(),
+        redaction: app_config.audio.transcription.redaction.to_redaction_config(),
+    }
+}
+
+/// Runs the benchmark command and writes its report.
+///
+/// # Arguments
+///
+/// * `app_config` - Application configuration with the benchmark runs
+/// * `args` - Command line arguments after `benchmark`:
+///   `[--audio-dir DIR] [--output-dir DIR] [--run NAME]...`
+///
+/// # Returns
+///
+/// * `Result<(), String>` - Ok once the report is written, or an error message
+fn run_benchmark(app_config: &AppConfig, args: &[String]) -> Result<(), String> {
+    let mut settings = app_config.benchmark.clone();
+    let mut only = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().cloned().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--audio-dir" => settings.audio_dir = value()?,
+            "--output-dir" => settings.output_dir = value()?,
+            "--run" => only.push(value()?),
+            _ => return Err(format!("Unknown benchmark argument \"{}\"", arg)),
+        }
+    }
+
+    // Default to the window length of live transcription
+    let recording = &app_config.audio.recording;
+    let live_window_seconds = app_config.audio.transcription.min_transcription_samples as f32
+        / (recording.output_sample_rate as f32 * recording.output_channels as f32);
+    let mut config = settings.to_benchmark_config(&transcription_config(app_config), live_window_seconds);
+    if !only.is_empty() {
+        config.runs.retain(|run| only.contains(&run.name));
+    }
+
+    let report = benchmark::run(&config)?;
+    let (json_path, csv_path) = benchmark::write_report(&report, &config.output_dir)?;
+    println!("Benchmark report written to {} and {}", json_path.display(), csv_path.display());
+    Ok(())
+}
+
+/// Runs the evaluate command and writes its report.
+///
+/// # Arguments
+///
+/// * `app_config` - Application configuration to evaluate
+/// * `args` - Command line arguments after `evaluate`: `[--audio-dir DIR] [--output-dir DIR]`
+///
+/// # Returns
+///
+/// * `Result<(), String>` - Ok once the report is written, or an error message
+fn run_evaluation(app_config: &AppConfig, args: &[String]) -> Result<(), String> {
+    let mut settings = app_config.evaluation.clone();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().cloned().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--audio-dir" => settings.audio_dir = value()?,
+            "--output-dir" => settings.output_dir = value()?,
+            _ => return Err(format!("Unknown evaluate argument \"{}\"", arg)),
+        }
+    }
+
+    let config = settings.to_evaluation_config(
+        &transcription_config(app_config),
+        app_config.audio.transcription.batch.window_seconds,
+    );
+    let report = evaluation::run(&config)?;
+    println!(
+        "{} files ({} failed): WER {:.1}%, CER {:.1}%",
+        report.files.len(),
+        report.failed,
+        report.words.error_rate * 100.0,
+        report.characters.error_rate * 100.0
+    );
+    let (json_path, csv_path) = evaluation::write_report(&report, &config.output_dir)?;
+    println!("Evaluation report written to {} and {}", json_path.display(), csv_path.display());
+    Ok(())
+}
+
 fn main() {
     // Define configuration file paths
     let config_path = "../config.yaml";
     
-    // Load configuration from file or use defaults
-    let app_config = AppConfig::load(config_path).unwrap_or_else(|e| {
-        eprintln!("Configuration error: {}. Using defaults.", e);
+    // Load configuration from file, or use defaults only if there is none; an
+    // invalid file must not silently turn off settings like encryption
+    let app_config = if Path::new(config_path).exists() {
+        AppConfig::load(config_path).unwrap_or_else(|e| {
+            eprintln!("Configuration error in {}: {}", config_path, e);
+            std::process::exit(1);
+        })
+    } else {
+        eprintln!("Config file not found at {}. Using defaults.", config_path);
         AppConfig::default()
+    };
+
+    // `benchmark` measures transcription speed and `evaluate` accuracy on recorded
+    // audio instead of starting the app
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command = match args.first().map(String::as_str) {
+        Some("benchmark") => Some(run_benchmark(&app_config, &args[1..]).map_err(|e| format!("Benchmark failed: {}", e))),
+        Some("evaluate") => Some(run_evaluation(&app_config, &args[1..]).map_err(|e| format!("Evaluation failed: {}", e))),
+        _ => None,
+    };
+    if let Some(result) = command {
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Set up encryption at rest before anything is written to disk
+    let encryption_config = EncryptionConfig {
+        enabled: app_config.encryption.enabled,
+        key_file: app_config.encryption.key_file.clone(),
+        passphrase_env: app_config.encryption.passphrase_env.clone(),
+    };
+    let encryptor = Encryptor::from_config(&encryption_config).unwrap_or_else(|e| {
+        eprintln!("Encryption error: {}", e);
+        std::process::exit(1);
     });
 
     // Create configurations for components
@@ -92,43 +403,80 @@ fn main() {
         output_sample_rate: app_config.audio.recording.output_sample_rate,
         output_channels: app_config.audio.recording.output_channels,
         output_bits_per_sample: app_config.audio.recording.output_bits_per_sample,
+        recordings_dir: app_config.audio.recording.recordings_dir.clone(),
+        retention: RetentionPolicy {
+            max_total_bytes: app_config.audio.recording.retention.max_total_bytes,
+            max_age: app_config.audio.recording.retention.max_age_days
+                .map(|days| std::time::Duration::from_secs(days * 24 * 60 * 60)),
+            delete_after_transcription: app_config.audio.recording.retention.delete_after_transcription,
+        },
+        encryptor,
     };
 
-    let transcription_config = TranscriptionConfig {
-        language: app_config.audio.transcription.language.clone(),
-        min_duration_seconds: app_config.audio.transcription.min_duration_seconds,
-        sample_rate: app_config.audio.transcription.whisper_sample_rate as usize,
-        model_path: app_config.audio.transcription.path_to_model.clone(),
-    };
+    let transcription_config = transcription_config(&app_config);
 
     let command_detector_config = CommandDetectorConfig::default();
 
     // Create component instances
     let recorder = Recorder::with_config(recorder_config);
     let processor = AudioProcessor::with_config(processor_config);
-    let _storage = AudioStorage::with_config(storage_config);
-    let transcription_service = TranscriptionService::with_config(transcription_config);
+    let storage = AudioStorage::with_config(storage_config);
+    let mut transcription_service = TranscriptionService::with_config(transcription_config);
     
-    // Initialize the transcription service
-    if let Err(e) = transcription_service.initialize() {
+    // Validate the transcription settings; the model itself is loaded once the window is up
+    if let Err(e) = transcription_service.prepare() {
         eprintln!("Failed to initialize transcription service: {}", e);
         std::process::exit(1);
     }
     
     let _command_detector = CommandDetector::with_config(command_detector_config);
 
+    // Apply the retention policy to recordings left from previous runs
+    if let Err(e) = storage.enforce_retention() {
+        eprintln!("Failed to apply retention policy: {}", e);
+    }
+
     // Create the orchestrator
     let orchestrator = Orchestrator::new(
         recorder,
         processor,
+        storage,
         transcription_service,
         app_config.clone(),
     );
     let orchestrator = Arc::new(Mutex::new(orchestrator));
+    let transcription_service = orchestrator.lock().unwrap().transcription_service();
 
     tauri::Builder::default()
         .manage(orchestrator.clone()) // Share the orchestrator state
-        .invoke_handler(tauri::generate_handler![start_recording, stop_recording])
+        .setup(move |app| {
+            // Load the model in the background so the window comes up immediately.
+            // A missing or broken model leaves the app running; switch_model can recover
+            let app = app.handle().clone();
+            std::thread::Builder::new()
+                .name("model-loader".to_string())
+                .spawn(move || {
+                    let _ = loader::load_model(&transcription_service, None, &|state| {
+                        emit_model_status(&app, state)
+                    });
+                })?;
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            start_recording,
+            stop_recording,
+            preview_retention,
+            export_file,
+            set_transcription_task,
+            get_vocabulary,
+            set_vocabulary,
+            list_models,
+            switch_model,
+            get_model_status,
+            transcribe_file,
+            cancel_file_transcription,
+            list_audio_tracks,
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -1,10 +1,19 @@
+use std::path::Path;
 use std::sync::{Arc, Mutex, mpsc};
+use std::time::Instant;
 use tokio::sync::mpsc as tokio_mpsc;
 use tokio::task;
 
 use crate::audio::recorder::Recorder;
 use crate::audio::processor::AudioProcessor;
-use crate::transcription::service::TranscriptionService;
+use crate::audio::storage::{AudioStorage, RetentionAction};
+use crate::transcription::batch::{BatchConfig, FileTranscriptionJob};
+use crate::transcription::loader::{ModelState, StatusReporter};
+use crate::transcription::models::{self, ModelInfo};
+use crate::transcription::queue::QueueDecision;
+use crate::transcription::segment::TranscriptSegment;
+use crate::transcription::service::{TranscriptionService, TranscriptionTask};
+use crate::transcription::worker::{TranscriptionJob, TranscriptionWorker};
 use crate::config::AppConfig;
 
 /// Orchestrator manages the high-level flow of the application.
@@ -14,16 +23,25 @@ pub struct Orchestrator {
     recorder: Arc<Mutex<Recorder>>,
     /// Audio processor component
     processor: Arc<Mutex<AudioProcessor>>,
+    /// Audio storage component
+    storage: Arc<Mutex<AudioStorage>>,
     /// Transcription service component
     transcription_service: Arc<Mutex<TranscriptionService>>,
     /// Global app configuration
     app_config: Arc<Mutex<AppConfig>>,
     /// Handle to the orchestration task
     orchestration_handle: Option<task::JoinHandle<()>>,
-    /// Flag indicating whether the orchestrator is active
+    /// Flag indicating whether a session runs, cleared only once a stopped session
+    /// has transcribed its last windows and been saved
     is_active: Arc<Mutex<bool>>,
     /// Signal to stop the orchestration
     stop_signal: Arc<Mutex<bool>>,
+    /// Text transcribed during the current session
+    session_transcript: Arc<Mutex<Vec<String>>>,
+    /// Signal to cancel the running file transcription
+    file_cancel_signal: Arc<Mutex<bool>>,
+    /// Flag indicating whether a file is being transcribed
+    file_job_active: Arc<Mutex<bool>>,
 }
 
 impl Orchestrator {
@@ -31,31 +49,88 @@ impl Orchestrator {
     pub fn new(
         recorder: Recorder,
         processor: AudioProcessor,
+        storage: AudioStorage,
         transcription_service: TranscriptionService,
         app_config: AppConfig,
     ) -> Self {
         Self {
             recorder: Arc::new(Mutex::new(recorder)),
             processor: Arc::new(Mutex::new(processor)),
+            storage: Arc::new(Mutex::new(storage)),
             transcription_service: Arc::new(Mutex::new(transcription_service)),
             app_config: Arc::new(Mutex::new(app_config)),
             orchestration_handle: None,
             is_active: Arc::new(Mutex::new(false)),
             stop_signal: Arc::new(Mutex::new(false)),
+            session_transcript: Arc::new(Mutex::new(Vec::new())),
+            file_cancel_signal: Arc::new(Mutex::new(false)),
+            file_job_active: Arc::new(Mutex::new(false)),
         }
     }
 
     /// Starts the orchestration process, recording audio and transcribing it.
-    pub fn start(&mut self, transcribe_channel: mpsc::Sender<String>) {
-        // Check if already active
+    ///
+    /// # Arguments
+    ///
+    /// * `transcribe_channel` - Receives transcribed segments, interim and final
+    /// * `decision_channel` - Receives the queue's decisions when transcription falls behind
+    /// * `model_status` - Receives status changes of models loaded during the session
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - Ok once recording runs, Err if a file is being transcribed
+    ///   or the previous session is still being saved
+    pub fn start(
+        &mut self,
+        transcribe_channel: mpsc::Sender<TranscriptSegment>,
+        decision_channel: mpsc::Sender<QueueDecision>,
+        model_status: StatusReporter,
+    ) -> Result<(), String> {
+        // Create a channel for audio data between recorder and processor
+        let channel_buffer_size = self.app_config.lock().unwrap().audio.performance.channel_buffer_size;
+        let (audio_sender, audio_receiver) = tokio_mpsc::channel(channel_buffer_size);
+        if !self.start_session(audio_receiver, transcribe_channel, decision_channel, model_status)? {
+            return Ok(());
+        }
+
+        // Start the recorder
+        {
+            let mut recorder = self.recorder.lock().unwrap();
+            recorder.start_recording(audio_sender);
+        }
+        Ok(())
+    }
+
+    /// Starts transcribing the audio arriving on a channel as a new session.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<bool, String>` - Whether a session was started, false if one already
+    ///   runs; Err if a file is being transcribed or the previous session is still being saved
+    fn start_session(
+        &mut self,
+        audio_receiver: tokio_mpsc::Receiver<Vec<f32>>,
+        transcribe_channel: mpsc::Sender<TranscriptSegment>,
+        decision_channel: mpsc::Sender<QueueDecision>,
+        model_status: StatusReporter,
+    ) -> Result<bool, String> {
+        // Check if already active; a stopped session keeps its buffers until it is saved
         {
             let active = self.is_active.lock().unwrap();
             if *active {
+                if *self.stop_signal.lock().unwrap() {
+                    return Err("The previous session is still being saved".to_string());
+                }
                 println!("Orchestrator already active");
-                return;
+                return Ok(false);
             }
         }
 
+        // A file job shares the transcription context, see `file_transcription`
+        if *self.file_job_active.lock().unwrap() {
+            return Err("Cannot record while a file is being transcribed".to_string());
+        }
+
         // Mark as active
         {
             let mut active = self.is_active.lock().unwrap();
@@ -68,59 +143,120 @@ impl Orchestrator {
             *stop_signal = false;
         }
 
+        // Start a fresh session
+        self.storage.lock().unwrap().clear();
+        self.session_transcript.lock().unwrap().clear();
+        self.transcription_service.lock().unwrap().reset_context();
+
         // Get configuration values
         let app_config = self.app_config.clone();
         let app_config_guard = app_config.lock().unwrap();
-        let channel_buffer_size = app_config_guard.audio.performance.channel_buffer_size;
-        
-        // Create a channel for audio data between recorder and processor
-        let (audio_sender, audio_receiver) = tokio_mpsc::channel(channel_buffer_size);
+        let sample_rate = app_config_guard.audio.transcription.whisper_sample_rate as f32;
+        let queue = app_config_guard.audio.performance.queue.to_queue_config(
+            app_config_guard.audio.performance.transcription_queue_size,
+            app_config_guard.audio.transcription.whisper_sample_rate,
+        );
+        let interim = app_config_guard.audio.transcription.interim.clone();
+        let diarization = app_config_guard.audio.transcription.diarization
+            .to_diarization_config(app_config_guard.audio.transcription.whisper_sample_rate);
+        drop(app_config_guard);
 
-        // Start the recorder
-        {
-            let mut recorder = self.recorder.lock().unwrap();
-            recorder.start_recording(audio_sender);
-        }
+        // Start the transcription worker; results come back through their own channel
+        let (result_sender, mut result_receiver) = tokio_mpsc::unbounded_channel();
+        let worker = TranscriptionWorker::spawn(
+            self.transcription_service.clone(),
+            queue,
+            result_sender,
+            diarization,
+            decision_channel,
+            model_status,
+        );
+
+        // Forward results to the UI as they arrive, and save the session once the
+        // worker has finished the last queued window
+        let storage = self.storage.clone();
+        let session_transcript = self.session_transcript.clone();
+        let forwarder = tokio::spawn(async move {
+            while let Some(segments) = result_receiver.recv().await {
+                for segment in segments {
+                    // Interim results are replaced by the final result of their window
+                    if segment.is_final && !segment.text.is_empty() {
+                        session_transcript.lock().unwrap().push(segment.text.clone());
+                    }
+
+                    // Send transcription result back
+                    if let Err(err) = transcribe_channel.send(segment) {
+                        println!("Failed to send transcription: {}", err);
+                    }
+                }
+            }
+            save_session(&storage, &session_transcript);
+        });
 
         // Clone needed values for the async task
         let processor = self.processor.clone();
-        let transcription_service = self.transcription_service.clone();
+        let storage = self.storage.clone();
         let stop_signal = self.stop_signal.clone();
-        let transcribe_channel_clone = transcribe_channel.clone();
+        let is_active = self.is_active.clone();
 
         // Start the orchestration task
         let handle = tokio::spawn(async move {
-            // Process audio chunks and transcribe them
+            // Process audio chunks and queue them for transcription
             let mut audio_receiver = audio_receiver;
             let mut consecutive_failures = 0;
+            // Session time at the start of the next processed window
+            let mut elapsed_seconds = 0.0;
+            // Id of the window currently being filled
+            let mut segment_id = 0;
+            let mut last_interim = Instant::now();
             
             while !*stop_signal.lock().unwrap() {
                 // Process audio chunks from recorder
                 match audio_receiver.recv().await {
                     Some(chunk) => {
+                        // Keep the raw audio for the session recording
+                        storage.lock().unwrap().add_samples(&chunk);
+
                         // Process the audio chunk
-                        match processor.lock().unwrap().process(chunk) {
+                        let processed = processor.lock().unwrap().process(chunk);
+                        match processed {
                             Some(processed_audio) => {
                                 // Reset failure counter on success
                                 consecutive_failures = 0;
                                 
-                                // Transcribe the processed audio
-                                match transcription_service.lock().unwrap().transcribe(&processed_audio) {
-                                    Some(text) => {
-                                        // Send transcription result back
-                                        if let Err(err) = transcribe_channel_clone.send(text) {
-                                            println!("Failed to send transcription: {}", err);
-                                        }
-                                    },
-                                    None => {
-                                        // Transcription returned none (not enough audio, etc.)
-                                        // This is expected in some cases, no action needed
-                                    }
+                                let window_start = elapsed_seconds;
+                                elapsed_seconds += processed_audio.len() as f32 / sample_rate;
+
+                                // Queue the processed audio; never waits, the queue makes room
+                                // if the worker falls behind
+                                let job = TranscriptionJob {
+                                    samples: processed_audio,
+                                    window_start,
+                                    segment_id,
+                                    is_final: true,
+                                };
+                                segment_id += 1;
+                                last_interim = Instant::now();
+                                if let Err(err) = worker.submit(job) {
+                                    println!("Failed to queue transcription: {}", err);
+                                    break;
                                 }
                             },
                             None => {
-                                // Processing returned none (not enough audio, etc.)
-                                // This is expected in some cases, no action needed
+                                // Not enough audio for a window yet; transcribe what we have
+                                // every so often for an interim result
+                                if interim.enabled && last_interim.elapsed().as_secs_f32() >= interim.interval_seconds {
+                                    last_interim = Instant::now();
+                                    let pending = processor.lock().unwrap().pending();
+                                    if let Some(samples) = pending {
+                                        worker.submit_interim(TranscriptionJob {
+                                            samples,
+                                            window_start: elapsed_seconds,
+                                            segment_id,
+                                            is_final: false,
+                                        });
+                                    }
+                                }
                             }
                         }
                     },
@@ -136,13 +272,40 @@ impl Orchestrator {
                     }
                 }
             }
+            // Transcribe the last, incomplete window so its interim result gets a final one
+            let remaining = processor.lock().unwrap().flush();
+            if let Some(samples) = remaining {
+                let job = TranscriptionJob {
+                    samples,
+                    window_start: elapsed_seconds,
+                    segment_id,
+                    is_final: true,
+                };
+                if let Err(err) = worker.submit(job) {
+                    println!("Failed to queue transcription: {}", err);
+                }
+            }
+
+            // Wait for the worker to finish the queued windows, off the async runtime
+            if let Err(err) = task::spawn_blocking(move || worker.shutdown()).await {
+                println!("Failed to shut down transcription worker: {}", err);
+            }
+            // The session's buffers may only be reused once it has been saved
+            if let Err(err) = forwarder.await {
+                println!("Failed to save session: {}", err);
+            }
+            *is_active.lock().unwrap() = false;
             println!("Orchestration task stopped");
         });
 
         self.orchestration_handle = Some(handle);
+        Ok(true)
     }
 
     /// Stops the orchestration process.
+    ///
+    /// The orchestrator stays active until the session has transcribed its last
+    /// windows and been saved; see `take_stopped_session` to wait for that.
     pub fn stop(&mut self) {
         // Check if active
         {
@@ -159,18 +322,223 @@ impl Orchestrator {
             *stop_signal = true;
         }
 
-        // Mark as inactive
-        {
-            let mut active = self.is_active.lock().unwrap();
-            *active = false;
-        }
-
         // Stop the recorder
         {
             let mut recorder = self.recorder.lock().unwrap();
             recorder.stop_recording();
         }
 
+        // The session is saved once the transcription worker has drained its queue
         println!("Orchestration stopped");
     }
-} 
\ No newline at end of file
+
+    /// Takes the handle of a session that was told to stop, to wait until it has been
+    /// saved before starting the next one.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<task::JoinHandle<()>>` - The stopped session's task, None if there
+    ///   is none or the session still runs
+    pub fn take_stopped_session(&mut self) -> Option<task::JoinHandle<()>> {
+        if *self.stop_signal.lock().unwrap() {
+            self.orchestration_handle.take()
+        } else {
+            None
+        }
+    }
+
+    /// Switches the transcription service between transcription and translation.
+    pub fn set_transcription_task(&self, task: TranscriptionTask) {
+        self.transcription_service.lock().unwrap().set_task(task);
+    }
+
+    /// Returns the terms of the custom vocabulary.
+    pub fn vocabulary(&self) -> Vec<String> {
+        self.transcription_service.lock().unwrap().vocabulary()
+    }
+
+    /// Replaces the terms of the custom vocabulary.
+    pub fn set_vocabulary(&self, terms: Vec<String>) -> Result<(), String> {
+        self.transcription_service.lock().unwrap().set_vocabulary(terms)
+    }
+
+    /// Lists the Whisper models found in the configured models directory.
+    pub fn list_models(&self) -> Result<Vec<ModelInfo>, String> {
+        let models_dir = self.app_config.lock().unwrap().audio.transcription.models_dir.clone();
+        models::list_models(Path::new(&models_dir))
+    }
+
+    /// Returns the loading status of the transcription model.
+    pub fn model_state(&self) -> ModelState {
+        self.transcription_service.lock().unwrap().model_state().clone()
+    }
+
+    /// Returns the shared transcription service, e.g. to swap its model without
+    /// holding the orchestrator while the model loads.
+    pub fn transcription_service(&self) -> Arc<Mutex<TranscriptionService>> {
+        self.transcription_service.clone()
+    }
+
+    /// Prepares the transcription of an audio file.
+    ///
+    /// The returned job runs without the orchestrator, so recording controls stay
+    /// responsive. Files can't be transcribed during a live session, since both
+    /// would share the transcription context.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<FileTranscriptionJob, String>` - The job, or an error if recording
+    ///   or already transcribing a file
+    pub fn file_transcription(&self) -> Result<FileTranscriptionJob, String> {
+        if *self.is_active.lock().unwrap() {
+            return Err("Cannot transcribe a file while recording".to_string());
+        }
+        {
+            let mut file_job_active = self.file_job_active.lock().unwrap();
+            if *file_job_active {
+                return Err("Another file is already being transcribed".to_string());
+            }
+            *file_job_active = true;
+        }
+        *self.file_cancel_signal.lock().unwrap() = false;
+
+        let app_config = self.app_config.lock().unwrap();
+        let transcription = &app_config.audio.transcription;
+        Ok(FileTranscriptionJob {
+            service: self.transcription_service.clone(),
+            config: BatchConfig {
+                window_seconds: transcription.batch.window_seconds,
+                sample_rate: transcription.whisper_sample_rate,
+                diarization: transcription.diarization.to_diarization_config(transcription.whisper_sample_rate),
+            },
+            cancel: self.file_cancel_signal.clone(),
+            active: self.file_job_active.clone(),
+        })
+    }
+
+    /// Cancels the running file transcription after its current window.
+    pub fn cancel_file_transcription(&self) {
+        *self.file_cancel_signal.lock().unwrap() = true;
+    }
+
+    /// Writes a stored recording or transcript back out as plaintext.
+    pub fn export_file(&self, source: &Path, destination: &Path) -> Result<(), String> {
+        self.storage.lock().unwrap().export(source, destination)
+    }
+
+    /// Lists the recordings the retention policy would currently delete.
+    pub fn preview_retention(&self) -> Result<Vec<RetentionAction>, String> {
+        self.storage.lock().unwrap().plan_retention()
+    }
+}
+
+/// Saves the recording and transcript of the session that just ended,
+/// then applies the retention policy.
+fn save_session(storage: &Mutex<AudioStorage>, session_transcript: &Mutex<Vec<String>>) {
+    let storage = storage.lock().unwrap();
+
+    match storage.save_session() {
+        Ok(Some(recording)) => {
+            let transcript = session_transcript.lock().unwrap().join("");
+            if !transcript.trim().is_empty() {
+                if let Err(err) = storage.save_transcript(&recording, &transcript) {
+                    println!("Failed to save transcript: {}", err);
+                }
+            }
+        },
+        Ok(None) => {},
+        Err(err) => println!("Failed to save recording: {}", err),
+    }
+
+    if let Err(err) = storage.enforce_retention() {
+        println!("Failed to apply retention policy: {}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::processor::ProcessorConfig;
+    use crate::audio::storage::StorageConfig;
+    use crate::transcription::mock::{MockEngine, MockEngineConfig};
+    use crate::transcription::engine::EngineKind;
+    use crate::transcription::service::TranscriptionConfig;
+    use std::collections::HashMap;
+    use std::fs;
+
+    /// An orchestrator on the mock engine, saving sessions to a directory unique to the test.
+    fn mock_orchestrator(name: &str, script: &[(&[f32], &str)]) -> (Orchestrator, std::path::PathBuf) {
+        let dir = std::env::temp_dir().join(format!("orchestrator-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let responses_by_input: HashMap<u64, String> = script
+            .iter()
+            .map(|(samples, text)| (MockEngine::fingerprint(samples), text.to_string()))
+            .collect();
+        let mut service = TranscriptionService::with_config(TranscriptionConfig {
+            engine: EngineKind::Mock,
+            mock: MockEngineConfig { responses_by_input, latency_ms: 50, ..MockEngineConfig::default() },
+            // The last window of a session is usually shorter than the others
+            min_duration_seconds: 0.5,
+            ..TranscriptionConfig::default()
+        });
+        service.initialize().unwrap();
+
+        let orchestrator = Orchestrator::new(
+            Recorder::new(),
+            AudioProcessor::with_config(ProcessorConfig { source_sample_rate: 16000, ..ProcessorConfig::default() }),
+            AudioStorage::with_config(StorageConfig {
+                recordings_dir: dir.to_string_lossy().to_string(),
+                ..StorageConfig::default()
+            }),
+            service,
+            AppConfig::default(),
+        );
+        (orchestrator, dir)
+    }
+
+    /// Starts a session on the given audio, as the recorder would send it.
+    fn start_session(orchestrator: &mut Orchestrator, audio: &[Vec<f32>]) -> Result<bool, String> {
+        let (audio_sender, audio_receiver) = tokio_mpsc::channel(audio.len().max(1));
+        for chunk in audio {
+            audio_sender.try_send(chunk.clone()).unwrap();
+        }
+        let (segments, _) = mpsc::channel();
+        let (decisions, _) = mpsc::channel();
+        orchestrator.start_session(audio_receiver, segments, decisions, Arc::new(|_| {}))
+    }
+
+    fn saved_transcripts(dir: &Path) -> Vec<String> {
+        let mut paths: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+            .collect();
+        paths.sort();
+        paths.iter().map(|path| fs::read_to_string(path).unwrap().trim().to_lowercase()).collect()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn a_stopped_session_is_saved_before_the_next_one_starts() {
+        let window = vec![0.1; 16000];
+        let tail = vec![0.1; 8000];
+        let (mut orchestrator, dir) =
+            mock_orchestrator("restart", &[(&window, "first window"), (&tail, "last words")]);
+
+        assert!(start_session(&mut orchestrator, &[window.clone(), tail.clone()]).unwrap());
+        tokio::time::sleep(tokio::time::Duration::from_millis(20)).await;
+        orchestrator.stop();
+
+        // The stopped session still transcribes its last window, so its buffers stay untouched
+        assert!(start_session(&mut orchestrator, &[vec![0.2; 16000]]).is_err());
+        orchestrator.take_stopped_session().unwrap().await.unwrap();
+        assert!(!*orchestrator.is_active.lock().unwrap());
+        assert_eq!(saved_transcripts(&dir), ["first window last words"]);
+
+        assert!(start_session(&mut orchestrator, &[vec![0.2; 16000]]).unwrap());
+        assert!(orchestrator.session_transcript.lock().unwrap().is_empty());
+        orchestrator.stop();
+        orchestrator.take_stopped_session().unwrap().await.unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
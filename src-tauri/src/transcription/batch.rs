@@ -0,0 +1,313 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+
+use crate::audio::decoder;
+use crate::audio::processor::{AudioProcessor, ProcessorConfig};
+use crate::transcription::diarization::{self, DiarizationConfig};
+use crate::transcription::segment::TranscriptSegment;
+use crate::transcription::service::TranscriptionService;
+
+/// Configuration for transcribing audio files
+#[derive(Clone)]
+pub struct BatchConfig {
+    /// Length of the windows the file is transcribed in, in seconds
+    pub window_seconds: f32,
+    /// Sample rate expected by the transcription model
+    pub sample_rate: u32,
+    /// Speaker labelling, clustered over the whole file
+    pub diarization: DiarizationConfig,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            window_seconds: 30.0,
+            sample_rate: 16000,
+            diarization: DiarizationConfig::default(),
+        }
+    }
+}
+
+/// Progress of a file transcription
+#[derive(Debug, Clone, Serialize)]
+pub struct FileProgress {
+    /// File being transcribed
+    pub path: String,
+    /// Seconds of audio transcribed so far
+    pub processed_seconds: f32,
+    /// Duration of the file in seconds as stored in the container; no less than
+    /// the audio transcribed so far if it isn't stored
+    pub total_seconds: f32,
+    /// Segments of the window that was just transcribed, in file time
+    pub segments: Vec<TranscriptSegment>,
+}
+
+/// Transcript of a whole audio file
+#[derive(Debug, Clone, Serialize)]
+pub struct FileTranscript {
+    /// File that was transcribed
+    pub path: String,
+    /// Duration of the file in seconds
+    pub duration_seconds: f32,
+    /// All segments, with times relative to the start of the file
+    pub segments: Vec<TranscriptSegment>,
+    /// Transcript with one `[start --> end] text` line per segment, the text
+    /// prefixed with the speaker if diarization is enabled
+    pub text: String,
+}
+
+/// Everything needed to transcribe a file without holding the orchestrator
+pub struct FileTranscriptionJob {
+    /// Service used for inference
+    pub service: Arc<Mutex<TranscriptionService>>,
+    /// Window and sample rate settings
+    pub config: BatchConfig,
+    /// Set to true to cancel the transcription after the current window
+    pub cancel: Arc<Mutex<bool>>,
+    /// Set while the job exists, so recording can't start until it is dropped
+    pub active: Arc<Mutex<bool>>,
+}
+
+impl FileTranscriptionJob {
+    /// Transcribes a file, see [`transcribe_file`].
+    pub fn run(
+        &self,
+        path: &Path,
+        track: Option<usize>,
+        progress: &dyn Fn(&FileProgress),
+    ) -> Result<FileTranscript, String> {
+        transcribe_file(path, track, &self.service, &self.config, &self.cancel, progress)
+    }
+}
+
+impl Drop for FileTranscriptionJob {
+    fn drop(&mut self) {
+        *self.active.lock().unwrap() = false;
+    }
+}
+
+/// Transcribes an audio file window by window.
+///
+/// The file is decoded while it is transcribed, so only one window of audio is
+/// held at a time. The audio goes through the same `AudioProcessor` resampling
+/// as live audio.
+/// Context carries over between windows like in a live session and is reset
+/// before and after the file.
+///
+/// # Arguments
+///
+/// * `path` - Audio or video file to transcribe
+/// * `track` - Index of the audio track to transcribe, None for the first one
+/// * `service` - Service used for inference, locked once per window
+/// * `config` - Window and sample rate settings
+/// * `cancel` - Checked before every decoded packet; true cancels the transcription
+/// * `progress` - Called after every window
+///
+/// # Returns
+///
+/// * `Result<FileTranscript, String>` - The transcript, or an error message if the file
+///   can't be read or the transcription was cancelled
+pub fn transcribe_file(
+    path: &Path,
+    track: Option<usize>,
+    service: &Mutex<TranscriptionService>,
+    config: &BatchConfig,
+    cancel: &Mutex<bool>,
+    progress: &dyn Fn(&FileProgress),
+) -> Result<FileTranscript, String> {
+    let mut audio = decoder::AudioDecoder::open(path, track)?;
+    let sample_rate = audio.sample_rate();
+    let channels = audio.channels().max(1) as usize;
+    let total_seconds = audio.duration_seconds().unwrap_or(0.0);
+    let path_name = path.display().to_string();
+    println!("Transcribing {} ({:.1}s)", path_name, total_seconds);
+
+    // Feed the processor whole windows; it resamples each one to the model's rate
+    let window_frames = ((config.window_seconds * sample_rate as f32) as usize).max(1);
+    let window_samples = window_frames * channels;
+    let processor = AudioProcessor::with_config(ProcessorConfig {
+        target_sample_rate: config.sample_rate,
+        target_channels: 1,
+        source_sample_rate: sample_rate,
+        source_channels: audio.channels(),
+        min_samples_for_processing: window_samples,
+        max_buffer_size: usize::MAX,
+    });
+
+    service.lock().unwrap().reset_context();
+
+    let mut segments = Vec::new();
+    // Speaker embedding of every segment, clustered once the whole file is transcribed
+    let mut embeddings = Vec::new();
+    let mut window_start = 0.0;
+    let mut window_index = 0;
+    let mut transcribe_window = |samples: Vec<f32>, input_samples: usize| {
+        let mut window_segments = TranscriptionService::transcribe_shared(service, &samples, true).unwrap_or_default();
+        if config.diarization.enabled {
+            embeddings.extend(window_segments.iter().map(|segment| {
+                diarization::segment_embedding(segment, &samples, &config.diarization)
+            }));
+        }
+        for segment in window_segments.iter_mut() {
+            segment.shift(window_start);
+            segment.id = window_index;
+        }
+        window_index += 1;
+        window_start += (input_samples / channels) as f32 / sample_rate as f32;
+
+        progress(&FileProgress {
+            path: path_name.clone(),
+            processed_seconds: window_start,
+            total_seconds: total_seconds.max(window_start),
+            segments: window_segments.clone(),
+        });
+        segments.extend(window_segments);
+    };
+
+    // Decode packet by packet and cut the audio into windows on the way, so only
+    // one window is held at a time
+    let mut buffered = 0;
+    loop {
+        if *cancel.lock().unwrap() {
+            service.lock().unwrap().reset_context();
+            return Err(format!("Transcription of {} cancelled", path_name));
+        }
+
+        let Some(packet) = audio.next_samples()? else {
+            // The last window is shorter than the rest
+            if let Some(samples) = processor.flush() {
+                transcribe_window(samples, buffered);
+            }
+            break;
+        };
+        let mut rest = packet.as_slice();
+        while !rest.is_empty() {
+            let take = (window_samples - buffered).min(rest.len());
+            buffered += take;
+            if let Some(samples) = processor.process(rest[..take].to_vec()) {
+                transcribe_window(samples, buffered);
+                buffered = 0;
+            }
+            rest = &rest[take..];
+        }
+    }
+    let duration_seconds = window_start;
+
+    service.lock().unwrap().reset_context();
+
+    if config.diarization.enabled {
+        let speakers = diarization::cluster(&embeddings, &config.diarization);
+        for (segment, speaker) in segments.iter_mut().zip(speakers) {
+            segment.speaker = speaker;
+        }
+    }
+
+    let text = segments
+        .iter()
+        .map(|segment| {
+            let speaker = segment
+                .speaker
+                .map(|speaker| format!("Speaker {}: ", speaker + 1))
+                .unwrap_or_default();
+            format!(
+                "[{} --> {}] {}{}",
+                format_timestamp(segment.start),
+                format_timestamp(segment.end),
+                speaker,
+                segment.text.trim()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Ok(FileTranscript {
+        path: path_name,
+        duration_seconds,
+        segments,
+        text,
+    })
+}
+
+/// Formats seconds as `hh:mm:ss.mmm`.
+fn format_timestamp(seconds: f32) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcription::engine::EngineKind;
+    use crate::transcription::mock::MockEngineConfig;
+    use crate::transcription::service::TranscriptionConfig;
+
+    /// Writes a mono WAV file of the given length at 16 kHz.
+    fn write_wav(name: &str, seconds: f32) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("batch-test-{}-{}.wav", name, std::process::id()));
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 16000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for i in 0..(seconds * 16000.0) as usize {
+            writer.write_sample(((i as f32 * 0.05).sin() * 8000.0) as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+        path
+    }
+
+    fn mock_service(responses: &[&str]) -> Mutex<TranscriptionService> {
+        let mut service = TranscriptionService::with_config(TranscriptionConfig {
+            engine: EngineKind::Mock,
+            mock: MockEngineConfig {
+                responses: responses.iter().map(|text| text.to_string()).collect(),
+                ..MockEngineConfig::default()
+            },
+            min_duration_seconds: 0.5,
+            ..TranscriptionConfig::default()
+        });
+        service.initialize().unwrap();
+        Mutex::new(service)
+    }
+
+    #[test]
+    fn files_are_transcribed_in_windows_of_file_time() {
+        let path = write_wav("windows", 2.5);
+        let service = mock_service(&["one", "two", "three"]);
+        let config = BatchConfig { window_seconds: 1.0, ..BatchConfig::default() };
+
+        let transcript = transcribe_file(&path, None, &service, &config, &Mutex::new(false), &|_| {}).unwrap();
+        let windows: Vec<(u64, f32, &str)> = transcript
+            .segments
+            .iter()
+            .map(|segment| (segment.id, segment.start, segment.text.trim()))
+            .collect();
+        assert_eq!(windows, [(0, 0.0, "one"), (1, 1.0, "two"), (2, 2.0, "three")]);
+        assert_eq!(transcript.duration_seconds, 2.5);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn cancelling_stops_the_decoding() {
+        let path = write_wav("cancel", 2.5);
+        let service = mock_service(&["one"]);
+        let config = BatchConfig { window_seconds: 1.0, ..BatchConfig::default() };
+        let cancel = Mutex::new(false);
+        // Cancel once the first window is done
+        let progress = |_: &FileProgress| *cancel.lock().unwrap() = true;
+
+        let result = transcribe_file(&path, None, &service, &config, &cancel, &progress);
+        assert!(result.unwrap_err().contains("cancelled"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,579 @@
+use std::f32::consts::PI;
+
+use crate::transcription::segment::TranscriptSegment;
+
+/// Analysis frame length in seconds
+const FRAME_SECONDS: f32 = 0.025;
+/// Step between analysis frames in seconds
+const HOP_SECONDS: f32 = 0.010;
+/// Number of mel filters
+const MEL_FILTERS: usize = 26;
+/// Number of cepstral coefficients kept, the energy coefficient excluded
+const CEPSTRAL_COEFFICIENTS: usize = 12;
+/// Frames this far below the loudest frame (in dB) are treated as silence
+const SILENCE_DB: f32 = 30.0;
+/// Pre-emphasis factor boosting high frequencies
+const PRE_EMPHASIS: f32 = 0.97;
+
+/// Configuration for speaker diarization
+#[derive(Clone)]
+pub struct DiarizationConfig {
+    /// Whether segments are labelled with speakers
+    pub enabled: bool,
+    /// Minimum cosine similarity (0.0 - 1.0) for a segment to join an existing speaker
+    pub similarity_threshold: f32,
+    /// Maximum number of speakers told apart in a live session
+    pub max_speakers: usize,
+    /// Number of speakers expected, if known: a file is split into exactly this
+    /// many, a live session tells at most this many apart
+    pub expected_speakers: Option<usize>,
+    /// Segments shorter than this (in seconds) take the speaker of the segment before them
+    pub min_segment_seconds: f32,
+    /// Sample rate of the audio
+    pub sample_rate: u32,
+}
+
+impl Default for DiarizationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            similarity_threshold: 0.85,
+            max_speakers: 8,
+            expected_speakers: None,
+            min_segment_seconds: 0.5,
+            sample_rate: 16000,
+        }
+    }
+}
+
+/// SpeakerTracker labels segments with speakers as they are transcribed.
+///
+/// Each speaker is represented by the running mean of its segment embeddings; a
+/// segment joins the most similar speaker, or starts a new one if none is
+/// similar enough.
+pub struct SpeakerTracker {
+    /// Configuration for the tracker
+    config: DiarizationConfig,
+    /// Sum of the embeddings of each speaker
+    centroids: Vec<Vec<f32>>,
+    /// Speaker of the last labelled segment
+    last_speaker: Option<u32>,
+}
+
+impl SpeakerTracker {
+    /// Creates a new SpeakerTracker with the specified configuration.
+    pub fn with_config(config: DiarizationConfig) -> Self {
+        Self {
+            config,
+            centroids: Vec::new(),
+            last_speaker: None,
+        }
+    }
+
+    /// Labels the segments of a window with speakers.
+    ///
+    /// # Arguments
+    ///
+    /// * `segments` - Segments with times relative to the start of `samples`
+    /// * `samples` - Audio of the window
+    pub fn assign(&mut self, segments: &mut [TranscriptSegment], samples: &[f32]) {
+        for segment in segments.iter_mut() {
+            let embedding = segment_embedding(segment, samples, &self.config);
+            let speaker = match embedding {
+                Some(embedding) => Some(self.assign_embedding(embedding)),
+                None => self.last_speaker,
+            };
+            segment.speaker = speaker;
+            self.last_speaker = speaker.or(self.last_speaker);
+        }
+    }
+
+    /// Finds or creates the speaker of an embedding and updates its centroid.
+    fn assign_embedding(&mut self, embedding: Vec<f32>) -> u32 {
+        let best = self
+            .centroids
+            .iter()
+            .enumerate()
+            .map(|(speaker, centroid)| (speaker, cosine_similarity(centroid, &embedding)))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+
+        let speaker = match best {
+            Some((speaker, similarity))
+                if similarity >= self.config.similarity_threshold
+                    || self.centroids.len() >= self.max_speakers() =>
+            {
+                for (sum, value) in self.centroids[speaker].iter_mut().zip(&embedding) {
+                    *sum += value;
+                }
+                speaker
+            }
+            _ => {
+                self.centroids.push(embedding);
+                self.centroids.len() - 1
+            }
+        };
+        speaker as u32
+    }
+
+    /// Number of speakers told apart, capped by the expected number if known.
+    fn max_speakers(&self) -> usize {
+        let max_speakers = match self.config.expected_speakers {
+            Some(expected) => expected.min(self.config.max_speakers),
+            None => self.config.max_speakers,
+        };
+        max_speakers.max(1)
+    }
+}
+
+/// Labels the segments of a whole recording with speakers.
+///
+/// Segments are clustered bottom-up, always merging the two most similar
+/// clusters, until `expected_speakers` clusters are left or no two clusters are
+/// similar enough. Speakers are numbered in order of first appearance.
+///
+/// # Arguments
+///
+/// * `embeddings` - Embedding of each segment, None for segments too short to embed
+/// * `config` - Diarization settings
+///
+/// # Returns
+///
+/// * `Vec<Option<u32>>` - Speaker of each segment, None if no segment could be embedded
+pub fn cluster(embeddings: &[Option<Vec<f32>>], config: &DiarizationConfig) -> Vec<Option<u32>> {
+    // Each cluster holds the sum of its embeddings and its member segments; merged
+    // clusters are left empty so indices stay stable
+    let mut clusters: Vec<Option<(Vec<f32>, Vec<usize>)>> = embeddings
+        .iter()
+        .enumerate()
+        .filter_map(|(index, embedding)| embedding.clone().map(|e| Some((e, vec![index]))))
+        .collect();
+    let count = clusters.len();
+
+    // Similarity of every pair of clusters as a row-major matrix (-inf for merged
+    // clusters), and the most similar other cluster of each; a merge only changes
+    // the merged cluster's row
+    let mut similarity = vec![f32::NEG_INFINITY; count * count];
+    let row = |i: usize| i * count..(i + 1) * count;
+    for i in 0..count {
+        for j in i + 1..count {
+            let value = cluster_similarity(&clusters, i, j);
+            similarity[i * count + j] = value;
+            similarity[j * count + i] = value;
+        }
+    }
+    let mut nearest: Vec<Option<(usize, f32)>> = (0..count).map(|i| most_similar(&similarity[row(i)], i)).collect();
+
+    let target = config.expected_speakers.unwrap_or(1).max(1);
+    let mut remaining = count;
+    while remaining > target {
+        let mut best: Option<(usize, usize, f32)> = None;
+        for (i, candidate) in nearest.iter().enumerate() {
+            if let Some((j, value)) = *candidate {
+                if best.map_or(true, |(_, _, best_value)| value > best_value) {
+                    best = Some((i, j, value));
+                }
+            }
+        }
+
+        let Some((i, j, value)) = best else { break };
+        if config.expected_speakers.is_none() && value < config.similarity_threshold {
+            break;
+        }
+
+        let (sum, members) = clusters[j].take().unwrap();
+        if let Some((total, merged)) = clusters[i].as_mut() {
+            for (total, value) in total.iter_mut().zip(&sum) {
+                *total += value;
+            }
+            merged.extend(members);
+        }
+        remaining -= 1;
+
+        for k in 0..count {
+            similarity[j * count + k] = f32::NEG_INFINITY;
+            similarity[k * count + j] = f32::NEG_INFINITY;
+            if k != i && k != j {
+                let value = cluster_similarity(&clusters, i, k);
+                similarity[i * count + k] = value;
+                similarity[k * count + i] = value;
+            }
+        }
+        nearest[j] = None;
+        nearest[i] = most_similar(&similarity[row(i)], i);
+        for k in 0..count {
+            if k == i || clusters[k].is_none() {
+                continue;
+            }
+            match nearest[k] {
+                // The nearest cluster changed, which may make another one the nearest
+                Some((other, _)) if other == i || other == j => nearest[k] = most_similar(&similarity[row(k)], k),
+                Some((_, value)) if similarity[k * count + i] > value => {
+                    nearest[k] = Some((i, similarity[k * count + i]));
+                }
+                _ => {}
+            }
+        }
+    }
+    let clusters: Vec<(Vec<f32>, Vec<usize>)> = clusters.into_iter().flatten().collect();
+
+    let mut cluster_of = vec![None; embeddings.len()];
+    for (cluster, (_, members)) in clusters.iter().enumerate() {
+        for &member in members {
+            cluster_of[member] = Some(cluster);
+        }
+    }
+
+    // Number speakers by first appearance; unembedded segments take the speaker before them
+    let mut order: Vec<usize> = Vec::new();
+    let mut speakers = Vec::with_capacity(embeddings.len());
+    let mut last = None;
+    for cluster in cluster_of {
+        let speaker = match cluster {
+            Some(cluster) => {
+                let position = order.iter().position(|&c| c == cluster).unwrap_or_else(|| {
+                    order.push(cluster);
+                    order.len() - 1
+                });
+                Some(position as u32)
+            }
+            None => last,
+        };
+        speakers.push(speaker);
+        last = speaker.or(last);
+    }
+
+    // Leading unembedded segments take the first speaker
+    let first = speakers.iter().flatten().next().copied();
+    for speaker in speakers.iter_mut() {
+        if speaker.is_some() {
+            break;
+        }
+        *speaker = first;
+    }
+    speakers
+}
+
+/// Cosine similarity of two clusters, -inf if either has been merged away.
+fn cluster_similarity(clusters: &[Option<(Vec<f32>, Vec<usize>)>], i: usize, j: usize) -> f32 {
+    match (&clusters[i], &clusters[j]) {
+        (Some((a, _)), Some((b, _))) => cosine_similarity(a, b),
+        _ => f32::NEG_INFINITY,
+    }
+}
+
+/// Most similar other cluster in a row of the similarity matrix, None if there is none.
+fn most_similar(row: &[f32], own: usize) -> Option<(usize, f32)> {
+    let mut best: Option<(usize, f32)> = None;
+    for (other, &value) in row.iter().enumerate() {
+        if other != own && value > f32::NEG_INFINITY && best.map_or(true, |(_, best_value)| value > best_value) {
+            best = Some((other, value));
+        }
+    }
+    best
+}
+
+/// Computes the embedding of a segment from the audio of its window.
+///
+/// # Arguments
+///
+/// * `segment` - Segment with times relative to the start of `samples`
+/// * `samples` - Audio of the window
+/// * `config` - Diarization settings
+///
+/// # Returns
+///
+/// * `Option<Vec<f32>>` - The embedding, None if the segment is too short or silent
+pub fn segment_embedding(
+    segment: &TranscriptSegment,
+    samples: &[f32],
+    config: &DiarizationConfig,
+) -> Option<Vec<f32>> {
+    if segment.end - segment.start < config.min_segment_seconds {
+        return None;
+    }
+
+    let rate = config.sample_rate as f32;
+    let start = ((segment.start * rate) as usize).min(samples.len());
+    let end = ((segment.end * rate) as usize).clamp(start, samples.len());
+    embed(&samples[start..end], config.sample_rate)
+}
+
+/// Computes a speaker embedding: the mean and standard deviation of the MFCCs of
+/// the voiced frames, normalized to unit length.
+///
+/// # Arguments
+///
+/// * `samples` - Mono audio
+/// * `sample_rate` - Sample rate of the audio
+///
+/// # Returns
+///
+/// * `Option<Vec<f32>>` - The embedding, None if there are too few voiced frames
+pub fn embed(samples: &[f32], sample_rate: u32) -> Option<Vec<f32>> {
+    let frame_len = (FRAME_SECONDS * sample_rate as f32) as usize;
+    let hop = (HOP_SECONDS * sample_rate as f32) as usize;
+    if frame_len == 0 || hop == 0 || samples.len() < frame_len {
+        return None;
+    }
+    let fft_size = frame_len.next_power_of_two();
+
+    let window: Vec<f32> = (0..frame_len)
+        .map(|i| 0.54 - 0.46 * (2.0 * PI * i as f32 / (frame_len - 1) as f32).cos())
+        .collect();
+    let filters = mel_filterbank(fft_size, sample_rate);
+
+    // Log mel energies and total energy of every frame
+    let mut frames: Vec<(f32, Vec<f32>)> = Vec::new();
+    let mut frame_start = 0;
+    while frame_start + frame_len <= samples.len() {
+        let frame = &samples[frame_start..frame_start + frame_len];
+
+        let mut re = vec![0.0; fft_size];
+        let mut im = vec![0.0; fft_size];
+        for i in 0..frame_len {
+            let previous = if i > 0 { frame[i - 1] } else { 0.0 };
+            re[i] = (frame[i] - PRE_EMPHASIS * previous) * window[i];
+        }
+        fft(&mut re, &mut im);
+
+        let power: Vec<f32> = (0..=fft_size / 2).map(|k| re[k] * re[k] + im[k] * im[k]).collect();
+        let energy: f32 = power.iter().sum();
+        let mel: Vec<f32> = filters
+            .iter()
+            .map(|filter| {
+                let sum: f32 = filter.iter().zip(&power).map(|(w, p)| w * p).sum();
+                (sum + 1e-10).ln()
+            })
+            .collect();
+
+        frames.push((10.0 * (energy + 1e-10).log10(), mel));
+        frame_start += hop;
+    }
+
+    // Only voiced frames describe the speaker
+    let loudest = frames.iter().map(|(db, _)| *db).fold(f32::MIN, f32::max);
+    let cepstra: Vec<Vec<f32>> = frames
+        .iter()
+        .filter(|(db, _)| *db >= loudest - SILENCE_DB)
+        .map(|(_, mel)| dct(mel))
+        .collect();
+    if cepstra.len() < 10 {
+        return None;
+    }
+
+    let n = cepstra.len() as f32;
+    let mut embedding = vec![0.0; CEPSTRAL_COEFFICIENTS * 2];
+    for c in 0..CEPSTRAL_COEFFICIENTS {
+        let mean = cepstra.iter().map(|frame| frame[c]).sum::<f32>() / n;
+        let variance = cepstra.iter().map(|frame| (frame[c] - mean).powi(2)).sum::<f32>() / n;
+        embedding[c] = mean;
+        embedding[CEPSTRAL_COEFFICIENTS + c] = variance.sqrt();
+    }
+
+    let norm = embedding.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm == 0.0 {
+        return None;
+    }
+    Some(embedding.into_iter().map(|v| v / norm).collect())
+}
+
+/// Cosine similarity of two vectors (1.0 = same direction).
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a * norm_b)
+}
+
+/// Triangular mel filters over the bins of a power spectrum.
+fn mel_filterbank(fft_size: usize, sample_rate: u32) -> Vec<Vec<f32>> {
+    let to_mel = |hz: f32| 2595.0 * (1.0 + hz / 700.0).log10();
+    let to_hz = |mel: f32| 700.0 * (10f32.powf(mel / 2595.0) - 1.0);
+
+    let bins = fft_size / 2 + 1;
+    let max_mel = to_mel(sample_rate as f32 / 2.0);
+    let centers: Vec<f32> = (0..MEL_FILTERS + 2)
+        .map(|i| to_hz(max_mel * i as f32 / (MEL_FILTERS + 1) as f32) * fft_size as f32 / sample_rate as f32)
+        .collect();
+
+    (0..MEL_FILTERS)
+        .map(|m| {
+            let (left, center, right) = (centers[m], centers[m + 1], centers[m + 2]);
+            (0..bins)
+                .map(|bin| {
+                    let bin = bin as f32;
+                    if bin <= left || bin >= right {
+                        0.0
+                    } else if bin <= center {
+                        (bin - left) / (center - left)
+                    } else {
+                        (right - bin) / (right - center)
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// DCT-II of the log mel energies, keeping the cepstral coefficients after c0.
+fn dct(mel: &[f32]) -> Vec<f32> {
+    let n = mel.len() as f32;
+    (1..=CEPSTRAL_COEFFICIENTS)
+        .map(|k| {
+            mel.iter()
+                .enumerate()
+                .map(|(i, value)| value * (PI * k as f32 * (i as f32 + 0.5) / n).cos())
+                .sum()
+        })
+        .collect()
+}
+
+/// In-place radix-2 FFT; the length must be a power of two.
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+
+    // Bit-reversal permutation
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * cos - im[b] * sin;
+                let t_im = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(expected_speakers: Option<usize>) -> DiarizationConfig {
+        DiarizationConfig { enabled: true, expected_speakers, ..DiarizationConfig::default() }
+    }
+
+    /// Two seconds of a synthetic voice: a jittery pulse train at the pitch `f0`,
+    /// shaped by resonators at the formant frequencies and broken into syllables.
+    fn voice(f0: f32, formants: &[f32], seed: u32) -> Vec<f32> {
+        let rate = 16000.0;
+        let mut state = seed;
+        let mut noise = move || {
+            state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+            (state >> 8) as f32 / (1 << 24) as f32 - 0.5
+        };
+
+        let mut source = vec![0.0; 32000];
+        let mut next_pulse = 0.0;
+        for (i, sample) in source.iter_mut().enumerate() {
+            if i as f32 >= next_pulse {
+                *sample = 1.0;
+                next_pulse += rate / (f0 * (1.0 + 0.05 * noise()));
+            }
+            *sample += 0.02 * noise();
+        }
+
+        let mut samples = source;
+        for &formant in formants {
+            // Two-pole resonator with a bandwidth of 100 Hz
+            let r = (-PI * 100.0 / rate).exp();
+            let (a1, a2) = (2.0 * r * (2.0 * PI * formant / rate).cos(), -r * r);
+            let (mut y1, mut y2) = (0.0, 0.0);
+            for sample in samples.iter_mut() {
+                let y = *sample + a1 * y1 + a2 * y2;
+                (y2, y1) = (y1, y);
+                *sample = y;
+            }
+        }
+
+        let peak = samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+        samples
+            .iter()
+            .enumerate()
+            .map(|(i, s)| {
+                // Syllables of a quarter second
+                let envelope = (PI * (i % 4000) as f32 / 4000.0).sin();
+                0.5 * envelope * s / peak
+            })
+            .collect()
+    }
+
+    #[test]
+    fn default_threshold_separates_synthetic_voices() {
+        let threshold = DiarizationConfig::default().similarity_threshold;
+        let low = [500.0, 1500.0, 2500.0];
+        let high = [850.0, 2000.0, 3200.0];
+        let first = embed(&voice(110.0, &low, 1), 16000).unwrap();
+        let second = embed(&voice(115.0, &low, 2), 16000).unwrap();
+        let other = embed(&voice(230.0, &high, 3), 16000).unwrap();
+
+        assert!(cosine_similarity(&first, &second) >= threshold);
+        assert!(cosine_similarity(&first, &other) < threshold);
+        assert!(cosine_similarity(&second, &other) < threshold);
+
+        let speakers = cluster(&[Some(first), Some(other), Some(second)], &config(None));
+        assert_eq!(speakers, [Some(0), Some(1), Some(0)]);
+    }
+
+    #[test]
+    fn similar_embeddings_share_a_speaker() {
+        let embeddings = vec![
+            Some(vec![1.0, 0.0, 0.0]),
+            Some(vec![0.0, 1.0, 0.0]),
+            None,
+            Some(vec![0.95, 0.05, 0.0]),
+            Some(vec![0.05, 0.95, 0.0]),
+            Some(vec![0.0, 0.0, 1.0]),
+        ];
+
+        let speakers = cluster(&embeddings, &config(None));
+        assert_eq!(speakers, [Some(0), Some(1), Some(1), Some(0), Some(1), Some(2)]);
+    }
+
+    #[test]
+    fn expected_speakers_sets_the_number_of_clusters() {
+        let embeddings = vec![
+            Some(vec![1.0, 0.0, 0.0]),
+            Some(vec![0.0, 1.0, 0.0]),
+            Some(vec![0.0, 0.0, 1.0]),
+            Some(vec![0.9, 0.1, 0.0]),
+        ];
+
+        let speakers = cluster(&embeddings, &config(Some(2)));
+        assert_eq!(speakers.iter().flatten().max(), Some(&1));
+        assert_eq!(speakers[0], speakers[3]);
+    }
+
+    #[test]
+    fn live_sessions_tell_at_most_the_expected_speakers_apart() {
+        let mut tracker = SpeakerTracker::with_config(config(Some(2)));
+
+        assert_eq!(tracker.assign_embedding(vec![1.0, 0.0, 0.0]), 0);
+        assert_eq!(tracker.assign_embedding(vec![0.0, 1.0, 0.0]), 1);
+        assert!(tracker.assign_embedding(vec![0.0, 0.0, 1.0]) < 2);
+    }
+}
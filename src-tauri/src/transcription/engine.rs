@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+
+use crate::transcription::segment::TranscriptSegment;
+use crate::transcription::service::TranscriptionTask;
+use crate::transcription::whisper::DecodingConfig;
+
+/// Which engine the transcription service uses
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EngineKind {
+    /// Whisper model loaded from a ggml file
+    #[default]
+    Whisper,
+    /// Scripted engine returning canned text, for tests and development
+    Mock,
+}
+
+/// Per-window options for a transcription run
+pub struct TranscribeOptions<'a> {
+    /// Language code such as "en" or "de", or "auto" to detect it
+    pub language: &'a str,
+    /// Whether to transcribe or translate to English
+    pub task: TranscriptionTask,
+    /// Text that conditions the decoder, e.g. previously transcribed text
+    pub initial_prompt: Option<&'a str>,
+    /// Decoding parameters
+    pub decoding: &'a DecodingConfig,
+}
+
+/// A speech-to-text backend used by the transcription service.
+pub trait TranscriptionEngine: Send {
+    /// Short name of the engine, used in logs.
+    fn name(&self) -> &str;
+
+    /// Transcribes the provided audio samples.
+    ///
+    /// # Arguments
+    ///
+    /// * `samples` - Audio samples as f32 values (16kHz, mono)
+    /// * `options` - Language, task, prompt and decoding parameters for this window
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<TranscriptSegment>, String>` - Transcribed segments, with times
+    ///   relative to the start of `samples`, or an error message
+    fn transcribe(
+        &mut self,
+        samples: &[f32],
+        options: &TranscribeOptions,
+    ) -> Result<Vec<TranscriptSegment>, String>;
+}
@@ -0,0 +1,402 @@
+use std::io::Write;
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+use crate::transcription::segment::{TranscriptSegment, TranscriptToken};
+
+/// Longest phrase, in words, checked for repetition loops
+const MAX_LOOP_WORDS: usize = 8;
+/// Characters that open a non-speech tag, with the character that closes it
+const TAG_DELIMITERS: [(char, char); 5] = [('[', ']'), ('(', ')'), ('*', '*'), ('♪', '♪'), ('♫', '♫')];
+
+/// Configuration for filtering hallucinations and artifacts out of Whisper output
+#[derive(Clone)]
+pub struct FilterConfig {
+    /// Whether the filter is applied at all
+    pub enabled: bool,
+    /// Whether to remove tags such as `[BLANK_AUDIO]`, `(music)` or `*laughs*`
+    pub remove_non_speech_tags: bool,
+    /// Segments consisting only of one of these phrases are dropped
+    pub phrases: Vec<String>,
+    /// Phrases repeated back to back more often than this are collapsed (None disables)
+    pub max_repetitions: Option<usize>,
+    /// Segments whose text compresses better than this ratio are dropped (None disables)
+    pub max_compression_ratio: Option<f32>,
+    /// Segments above this no-speech probability are dropped if also below
+    /// `logprob_threshold` (None disables)
+    pub no_speech_threshold: Option<f32>,
+    /// Segments whose audio has an RMS level below this are dropped if also below
+    /// `logprob_threshold`; used when the engine reports no no-speech probability
+    /// (None disables)
+    pub silence_threshold: Option<f32>,
+    /// Average log-probability below which a likely no-speech segment is dropped
+    pub logprob_threshold: f32,
+}
+
+impl Default for FilterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            remove_non_speech_tags: true,
+            phrases: default_phrases(),
+            max_repetitions: Some(3),
+            max_compression_ratio: Some(2.4),
+            no_speech_threshold: Some(0.6),
+            silence_threshold: Some(0.01),
+            logprob_threshold: -1.0,
+        }
+    }
+}
+
+/// Phrases Whisper commonly produces on silence, learned from subtitled videos.
+pub fn default_phrases() -> Vec<String> {
+    [
+        "Thank you for watching.",
+        "Thanks for watching!",
+        "Please subscribe to my channel.",
+        "Subtitles by the Amara.org community",
+        "Untertitel der Amara.org-Community",
+        "Untertitel im Auftrag des ZDF, 2017",
+    ]
+    .iter()
+    .map(|phrase| phrase.to_string())
+    .collect()
+}
+
+/// Why a segment was dropped
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FilterReason {
+    /// Nothing but non-speech tags
+    NonSpeech,
+    /// A known hallucination phrase
+    Phrase,
+    /// Text compresses too well, i.e. is highly repetitive
+    CompressionRatio,
+    /// The decoder was likely transcribing silence
+    NoSpeech,
+}
+
+/// HallucinationFilter removes text Whisper produces without corresponding speech.
+pub struct HallucinationFilter {
+    /// Configuration for the filter
+    config: FilterConfig,
+    /// Normalized phrases to drop
+    phrases: Vec<String>,
+}
+
+impl HallucinationFilter {
+    /// Creates a new HallucinationFilter with the specified configuration.
+    pub fn with_config(config: FilterConfig) -> Self {
+        let phrases = config
+            .phrases
+            .iter()
+            .map(|phrase| normalize(phrase))
+            .filter(|phrase| !phrase.is_empty())
+            .collect();
+        Self { config, phrases }
+    }
+
+    /// Filters the segments of a window, dropping or cleaning up hallucinated text.
+    ///
+    /// # Arguments
+    ///
+    /// * `segments` - Segments as returned by the engine
+    /// * `samples` - Audio of the window, used to tell silence from speech
+    /// * `sample_rate` - Sample rate of `samples`
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<TranscriptSegment>` - The segments that contain speech
+    pub fn apply(&self, segments: Vec<TranscriptSegment>, samples: &[f32], sample_rate: usize) -> Vec<TranscriptSegment> {
+        if !self.config.enabled {
+            return segments;
+        }
+
+        segments
+            .into_iter()
+            .filter_map(|mut segment| match self.check(&mut segment, samples, sample_rate) {
+                Some(reason) => {
+                    println!("Dropped segment ({:?}): {}", reason, segment.text.trim());
+                    None
+                }
+                None => Some(segment),
+            })
+            .collect()
+    }
+
+    /// Applies the rules to one segment, cleaning it up in place.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<FilterReason>` - Why the segment should be dropped, None to keep it
+    fn check(&self, segment: &mut TranscriptSegment, samples: &[f32], sample_rate: usize) -> Option<FilterReason> {
+        if segment.avg_logprob < self.config.logprob_threshold && self.is_silent(segment, samples, sample_rate) {
+            return Some(FilterReason::NoSpeech);
+        }
+
+        if let Some(max_ratio) = self.config.max_compression_ratio {
+            if compression_ratio(&segment.text) > max_ratio {
+                return Some(FilterReason::CompressionRatio);
+            }
+        }
+
+        // Segments without tokens are treated as one token per word
+        let has_tokens = !segment.tokens.is_empty();
+        let mut words = if has_tokens {
+            segment.take_words()
+        } else {
+            split_words(&segment.text, segment.start, segment.end)
+        };
+
+        if self.config.remove_non_speech_tags {
+            words = remove_tags(words);
+        }
+        if let Some(max_repetitions) = self.config.max_repetitions {
+            words = collapse_repetitions(words, max_repetitions.max(1));
+        }
+
+        let tokens: Vec<TranscriptToken> = words.into_iter().flatten().collect();
+        if has_tokens {
+            segment.set_tokens(tokens);
+        } else {
+            segment.text = tokens.into_iter().map(|t| t.text).collect();
+        }
+
+        let text = normalize(&segment.text);
+        if text.is_empty() {
+            return Some(FilterReason::NonSpeech);
+        }
+        if self.phrases.contains(&text) {
+            return Some(FilterReason::Phrase);
+        }
+        None
+    }
+
+    /// Checks whether a segment likely covers no speech: by the engine's no-speech
+    /// probability if it reports one, otherwise by the level of the segment's audio.
+    fn is_silent(&self, segment: &TranscriptSegment, samples: &[f32], sample_rate: usize) -> bool {
+        if let Some(probability) = segment.no_speech_prob {
+            return self.config.no_speech_threshold.is_some_and(|threshold| probability > threshold);
+        }
+        let Some(threshold) = self.config.silence_threshold else {
+            return false;
+        };
+
+        // Segment times are relative to the window; fall back to the whole window
+        let to_index = |seconds: f32| ((seconds.max(0.0) * sample_rate as f32) as usize).min(samples.len());
+        let (start, end) = (to_index(segment.start), to_index(segment.end));
+        let audio = if start < end { &samples[start..end] } else { samples };
+        rms(audio) < threshold
+    }
+}
+
+/// Root mean square level of audio, 0.0 for no audio.
+fn rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+/// Removes words that are part of a non-speech tag, e.g. `[BLANK_AUDIO]` or `(upbeat music)`.
+///
+/// A tag whose closing character never comes is kept, so a stray bracket doesn't
+/// swallow the rest of the segment.
+fn remove_tags(words: Vec<Vec<TranscriptToken>>) -> Vec<Vec<TranscriptToken>> {
+    let mut kept = Vec::with_capacity(words.len());
+    // Words of the open tag, with the character that closes it
+    let mut open_tag: Option<(char, Vec<Vec<TranscriptToken>>)> = None;
+
+    for word in words {
+        let text: String = word.iter().map(|t| t.text.as_str()).collect();
+        let text = text.trim().trim_end_matches(['.', ',', '!', '?']).to_string();
+
+        match open_tag.as_mut() {
+            Some((close, tag_words)) => {
+                if text.ends_with(*close) {
+                    open_tag = None;
+                } else {
+                    tag_words.push(word);
+                }
+            }
+            None => {
+                let tag = TAG_DELIMITERS.iter().find(|(open, _)| text.starts_with(*open));
+                match tag {
+                    Some(&(open, close)) => {
+                        // The tag continues unless this word also closes it
+                        let rest = &text[open.len_utf8()..];
+                        if rest.is_empty() || !rest.ends_with(close) {
+                            open_tag = Some((close, vec![word]));
+                        }
+                    }
+                    None => kept.push(word),
+                }
+            }
+        }
+    }
+
+    if let Some((_, tag_words)) = open_tag {
+        kept.extend(tag_words);
+    }
+    kept
+}
+
+/// Collapses phrases repeated back to back more than `max_repetitions` times into one.
+fn collapse_repetitions(mut words: Vec<Vec<TranscriptToken>>, max_repetitions: usize) -> Vec<Vec<TranscriptToken>> {
+    for n in 1..=MAX_LOOP_WORDS {
+        let mut keys: Vec<String> = words.iter().map(|word| word_key(word)).collect();
+        let mut i = 0;
+        while i + n <= keys.len() {
+            let pattern = &keys[i..i + n];
+            if pattern.iter().all(String::is_empty) {
+                i += 1;
+                continue;
+            }
+
+            let mut repeats = 1;
+            while i + (repeats + 1) * n <= keys.len() && keys[i + repeats * n..i + (repeats + 1) * n] == *pattern {
+                repeats += 1;
+            }
+
+            // Keep the last repetition, which carries any trailing punctuation
+            if repeats > max_repetitions {
+                words.drain(i..i + (repeats - 1) * n);
+                keys.drain(i..i + (repeats - 1) * n);
+            }
+            i += 1;
+        }
+    }
+    words
+}
+
+/// Lowercased letters and digits of a word, used to compare words.
+fn word_key(word: &[TranscriptToken]) -> String {
+    word.iter()
+        .flat_map(|token| token.text.chars())
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Splits text into single-token words, each carrying the whitespace before it
+/// and spanning the whole segment.
+fn split_words(text: &str, start: f32, end: f32) -> Vec<Vec<TranscriptToken>> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    for c in text.chars() {
+        if c.is_whitespace() && current.chars().any(|c| !c.is_whitespace()) {
+            words.push(std::mem::take(&mut current));
+        }
+        current.push(c);
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+
+    words
+        .into_iter()
+        .map(|text| vec![TranscriptToken { text, probability: 1.0, start, end }])
+        .collect()
+}
+
+/// Lowercases text and reduces it to words separated by single spaces.
+fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .map(|word| {
+            word.chars()
+                .filter(|c| c.is_alphanumeric())
+                .flat_map(char::to_lowercase)
+                .collect::<String>()
+        })
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Ratio of the text's size to its zlib-compressed size, as used by Whisper to
+/// detect repetitive output.
+fn compression_ratio(text: &str) -> f32 {
+    let bytes = text.trim().as_bytes();
+    if bytes.is_empty() {
+        return 0.0;
+    }
+
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    match encoder.write_all(bytes).and_then(|_| encoder.finish()) {
+        Ok(compressed) if !compressed.is_empty() => bytes.len() as f32 / compressed.len() as f32,
+        _ => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcription::service::TranscriptionTask;
+
+    const SAMPLE_RATE: usize = 16_000;
+
+    fn segment(text: &str, avg_logprob: f32) -> TranscriptSegment {
+        TranscriptSegment {
+            start: 0.0,
+            end: 1.0,
+            text: text.to_string(),
+            tokens: Vec::new(),
+            words: Vec::new(),
+            avg_logprob,
+            no_speech_prob: None,
+            language: "en".to_string(),
+            language_probability: None,
+            task: TranscriptionTask::Transcribe,
+            id: 0,
+            is_final: true,
+            speaker: None,
+            spoken_text: None,
+            redactions: Vec::new(),
+        }
+    }
+
+    fn texts(segments: Vec<TranscriptSegment>) -> Vec<String> {
+        segments.into_iter().map(|segment| segment.text.trim().to_string()).collect()
+    }
+
+    #[test]
+    fn closed_tags_are_removed() {
+        let filter = HallucinationFilter::with_config(FilterConfig::default());
+        let audio = vec![0.1; SAMPLE_RATE];
+        let segments = vec![segment(" So [BLANK_AUDIO] we (upbeat music) start", 0.0)];
+
+        assert_eq!(texts(filter.apply(segments, &audio, SAMPLE_RATE)), ["So we start"]);
+    }
+
+    #[test]
+    fn unclosed_tags_keep_the_following_words() {
+        let filter = HallucinationFilter::with_config(FilterConfig::default());
+        let audio = vec![0.1; SAMPLE_RATE];
+        let segments = vec![segment(" We met (in the morning and left", 0.0)];
+
+        assert_eq!(texts(filter.apply(segments, &audio, SAMPLE_RATE)), ["We met (in the morning and left"]);
+    }
+
+    #[test]
+    fn unlikely_text_on_silence_is_dropped() {
+        let filter = HallucinationFilter::with_config(FilterConfig::default());
+        let silence = vec![0.0; SAMPLE_RATE];
+        let speech = vec![0.1; SAMPLE_RATE];
+
+        assert!(filter.apply(vec![segment(" So", -1.5)], &silence, SAMPLE_RATE).is_empty());
+        // Confident text on silence and unlikely text on speech are both kept
+        assert_eq!(filter.apply(vec![segment(" So", -0.2)], &silence, SAMPLE_RATE).len(), 1);
+        assert_eq!(filter.apply(vec![segment(" So", -1.5)], &speech, SAMPLE_RATE).len(), 1);
+    }
+
+    #[test]
+    fn the_engines_no_speech_probability_takes_precedence() {
+        let filter = HallucinationFilter::with_config(FilterConfig::default());
+        let silence = vec![0.0; SAMPLE_RATE];
+        let mut likely_speech = segment(" So", -1.5);
+        likely_speech.no_speech_prob = Some(0.1);
+
+        assert_eq!(filter.apply(vec![likely_speech], &silence, SAMPLE_RATE).len(), 1);
+    }
+}
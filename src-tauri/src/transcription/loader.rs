@@ -0,0 +1,211 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+
+use crate::transcription::engine::EngineKind;
+use crate::transcription::service::TranscriptionService;
+
+/// Lifecycle of the transcription model
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelStatus {
+    /// No load has been started yet
+    #[default]
+    NotLoaded,
+    /// The model is being loaded (and warmed up)
+    Loading,
+    /// The model is ready for transcription
+    Ready,
+    /// The model file does not exist; a model can still be switched to
+    Missing,
+    /// Loading the model failed
+    Failed,
+}
+
+/// Status of the transcription model, as reported to the frontend
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ModelState {
+    /// Current status
+    pub status: ModelStatus,
+    /// Path of the model the status refers to (empty for non-Whisper engines)
+    pub model_path: String,
+    /// Error message for the missing and failed states, or of a failed switch
+    /// while the previous model stays ready
+    pub error: Option<String>,
+    /// Load in progress; while switching models the status stays `Ready` for the
+    /// model still in use and this describes the model being switched to
+    pub loading: Option<LoadProgress>,
+}
+
+impl ModelState {
+    fn new(status: ModelStatus, model_path: &str, error: Option<String>) -> Self {
+        Self {
+            status,
+            model_path: model_path.to_string(),
+            error,
+            loading: None,
+        }
+    }
+}
+
+/// Receives model status changes from loads started outside a command, such as
+/// the fallback model switch
+pub type StatusReporter = Arc<dyn Fn(&ModelState) + Send + Sync>;
+
+/// Step of a model load
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoadStage {
+    /// Reading the model file from disk
+    Reading,
+    /// Building the engine from the model
+    Initializing,
+    /// Running a short inference so the first window is not slow
+    WarmingUp,
+}
+
+/// Progress of a model load, as reported to the frontend
+#[derive(Debug, Clone, Serialize)]
+pub struct LoadProgress {
+    /// Path of the model being loaded
+    pub model_path: String,
+    /// Current step
+    pub stage: LoadStage,
+    /// Fraction of the load done (0.0 - 1.0)
+    pub progress: f32,
+}
+
+/// Share of the load progress taken by reading the model file
+const READ_SHARE: f32 = 0.7;
+/// Progress once the engine is built
+const INITIALIZED: f32 = 0.9;
+/// Size of the chunks the model file is read in
+const READ_CHUNK: usize = 8 * 1024 * 1024;
+
+/// Loads the transcription engine and swaps it into the service.
+///
+/// The service is only locked briefly to read its configuration and to swap the
+/// engine in, so transcription and other commands stay responsive while the
+/// model loads. If loading fails, the previous engine (if any) stays in use.
+///
+/// While a previous model is ready, every report keeps it `Ready` and describes
+/// the switch in `loading`; otherwise the status is `Loading`.
+///
+/// # Arguments
+///
+/// * `service` - Service receiving the engine
+/// * `model_path` - Whisper model to switch to, or None to load the configured engine
+/// * `report` - Called with every status change, e.g. to emit an event
+///
+/// # Returns
+///
+/// * `Result<(), String>` - Ok once the engine is in use, Err with error message otherwise
+pub fn load_model(
+    service: &Mutex<TranscriptionService>,
+    model_path: Option<String>,
+    report: &dyn Fn(&ModelState),
+) -> Result<(), String> {
+    let (mut config, previous) = {
+        let service = service.lock().unwrap();
+        (service.config().clone(), service.model_state().clone())
+    };
+    if let Some(model_path) = model_path {
+        config.engine = EngineKind::Whisper;
+        config.model_path = model_path;
+    }
+    let model_path = match config.engine {
+        EngineKind::Whisper => config.model_path.clone(),
+        EngineKind::Mock => String::new(),
+    };
+
+    let previous_ready = previous.status == ModelStatus::Ready;
+
+    let update = |state: ModelState| {
+        service.lock().unwrap().set_model_state(state.clone());
+        report(&state);
+    };
+    let progress = |stage: LoadStage, progress: f32| {
+        let loading = Some(LoadProgress {
+            model_path: model_path.clone(),
+            stage,
+            progress,
+        });
+        let state = if previous_ready {
+            ModelState { error: None, loading, ..previous.clone() }
+        } else {
+            ModelState { loading, ..ModelState::new(ModelStatus::Loading, &model_path, None) }
+        };
+        update(state);
+    };
+    // Report the failure, but keep reporting the previous engine as ready if it is still in use
+    let fail = |status: ModelStatus, error: String| {
+        println!("Failed to load transcription model: {}", error);
+        if previous_ready {
+            update(ModelState { error: Some(error.clone()), loading: None, ..previous.clone() });
+        } else {
+            update(ModelState::new(status, &model_path, Some(error.clone())));
+        }
+        Err(error)
+    };
+
+    progress(LoadStage::Reading, 0.0);
+
+    if config.engine == EngineKind::Whisper {
+        if !Path::new(&model_path).exists() {
+            return fail(ModelStatus::Missing, format!("Model file {} not found", model_path));
+        }
+        // Reading the file up front pulls it into the page cache, so the engine
+        // loads it from memory and the read can report real progress
+        let read = read_model_file(&model_path, &|fraction| {
+            progress(LoadStage::Reading, fraction * READ_SHARE)
+        });
+        if let Err(err) = read {
+            return fail(ModelStatus::Failed, err);
+        }
+    }
+
+    progress(LoadStage::Initializing, READ_SHARE);
+    let mut engine = match TranscriptionService::create_engine(&config) {
+        Ok(engine) => engine,
+        Err(err) => return fail(ModelStatus::Failed, err),
+    };
+    // Warming up the mock engine would consume one of its scripted responses
+    if config.warm_up && config.engine == EngineKind::Whisper {
+        progress(LoadStage::WarmingUp, INITIALIZED);
+        TranscriptionService::warm_up(engine.as_mut(), &config);
+    }
+
+    service.lock().unwrap().set_engine(engine, &config);
+    update(ModelState::new(ModelStatus::Ready, &model_path, None));
+    Ok(())
+}
+
+/// Reads a model file to the end, reporting the fraction read every 5%.
+fn read_model_file(model_path: &str, report: &dyn Fn(f32)) -> Result<(), String> {
+    let mut file = File::open(model_path)
+        .map_err(|e| format!("Failed to open model file {}: {}", model_path, e))?;
+    let size = file.metadata()
+        .map_err(|e| format!("Failed to read model file {}: {}", model_path, e))?
+        .len()
+        .max(1);
+
+    let mut buffer = vec![0u8; READ_CHUNK];
+    let mut read = 0u64;
+    let mut reported = 0.0;
+    loop {
+        let n = file.read(&mut buffer)
+            .map_err(|e| format!("Failed to read model file {}: {}", model_path, e))?;
+        if n == 0 {
+            return Ok(());
+        }
+        read += n as u64;
+        let fraction = (read as f32 / size as f32).min(1.0);
+        if fraction - reported >= 0.05 {
+            reported = fraction;
+            report(fraction);
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::thread;
+use std::time::Duration;
+
+use crate::transcription::engine::{TranscribeOptions, TranscriptionEngine};
+use crate::transcription::segment::{TranscriptSegment, TranscriptToken};
+
+/// Sample rate the mock assumes when computing segment times
+const SAMPLE_RATE: f32 = 16_000.0;
+
+/// Configuration for the mock engine
+#[derive(Clone, Default)]
+pub struct MockEngineConfig {
+    /// Canned responses for specific audio, keyed by `MockEngine::fingerprint` of the
+    /// samples, so a window gets its text no matter how many other windows ran before
+    pub responses_by_input: HashMap<u64, String>,
+    /// Canned responses for any other audio; the n-th such window gets the n-th
+    /// response, cycling when exhausted
+    pub responses: Vec<String>,
+    /// Simulated inference time per window in milliseconds
+    pub latency_ms: u64,
+    /// Fail every n-th window (never fails if None)
+    pub fail_every: Option<u32>,
+}
+
+/// MockEngine is a deterministic, scriptable engine that needs no model file.
+pub struct MockEngine {
+    /// Configuration for the mock
+    config: MockEngineConfig,
+    /// Number of windows transcribed so far
+    calls: u32,
+    /// Number of windows answered from the cycling responses so far
+    unscripted_calls: usize,
+}
+
+impl MockEngine {
+    /// Creates a new MockEngine with the specified configuration.
+    pub fn with_config(config: MockEngineConfig) -> Self {
+        Self { config, calls: 0, unscripted_calls: 0 }
+    }
+
+    /// Fingerprint of a window's samples (FNV-1a over their bits), the key of
+    /// `MockEngineConfig::responses_by_input`.
+    pub fn fingerprint(samples: &[f32]) -> u64 {
+        samples.iter().fold(0xcbf2_9ce4_8422_2325, |hash, sample| {
+            (hash ^ u64::from(sample.to_bits())).wrapping_mul(0x0100_0000_01b3)
+        })
+    }
+
+    /// Picks the response for a window, None if there is nothing to say.
+    fn response(&mut self, samples: &[f32]) -> Option<String> {
+        if let Some(response) = self.config.responses_by_input.get(&Self::fingerprint(samples)) {
+            return Some(response.clone());
+        }
+        if self.config.responses.is_empty() {
+            return None;
+        }
+        let index = self.unscripted_calls % self.config.responses.len();
+        self.unscripted_calls += 1;
+        Some(self.config.responses[index].clone())
+    }
+}
+
+impl TranscriptionEngine for MockEngine {
+    fn name(&self) -> &str {
+        "mock"
+    }
+
+    fn transcribe(
+        &mut self,
+        samples: &[f32],
+        options: &TranscribeOptions,
+    ) -> Result<Vec<TranscriptSegment>, String> {
+        self.calls += 1;
+
+        if self.config.latency_ms > 0 {
+            thread::sleep(Duration::from_millis(self.config.latency_ms));
+        }
+
+        if let Some(every) = self.config.fail_every {
+            if every > 0 && self.calls % every == 0 {
+                return Err(format!("Mock failure on window {}", self.calls));
+            }
+        }
+
+        let response = match self.response(samples) {
+            Some(response) => response,
+            None => return Ok(Vec::new()),
+        };
+
+        // Spread the words evenly over the window
+        let duration = samples.len() as f32 / SAMPLE_RATE;
+        let words: Vec<&str> = response.split_whitespace().collect();
+        let word_duration = duration / words.len().max(1) as f32;
+        let tokens: Vec<TranscriptToken> = words
+            .iter()
+            .enumerate()
+            .map(|(i, word)| TranscriptToken {
+                text: format!(" {}", word),
+                probability: 1.0,
+                start: i as f32 * word_duration,
+                end: (i + 1) as f32 * word_duration,
+            })
+            .collect();
+        let text = tokens.iter().map(|token| token.text.as_str()).collect();
+
+        Ok(vec![TranscriptSegment {
+            start: 0.0,
+            end: duration,
+            text,
+            tokens,
+            words: Vec::new(),
+            avg_logprob: 0.0,
+            no_speech_prob: None,
+            language: options.language.to_string(),
+            language_probability: None,
+            task: options.task,
+            id: 0,
+            is_final: true,
+            speaker: None,
+            spoken_text: None,
+            redactions: Vec::new(),
+        }])
+    }
+}
@@ -1,4 +1,30 @@
 #[allow(dead_code)]
+pub mod batch;
+#[allow(dead_code)]
+pub mod diarization;
+#[allow(dead_code)]
+pub mod engine;
+#[allow(dead_code)]
+pub mod filter;
+#[allow(dead_code)]
+pub mod loader;
+#[allow(dead_code)]
+pub mod mock;
+#[allow(dead_code)]
+pub mod models;
+#[allow(dead_code)]
+pub mod normalize;
+#[allow(dead_code)]
+pub mod queue;
+#[allow(dead_code)]
+pub mod redaction;
+#[allow(dead_code)]
 pub mod service;
 #[allow(dead_code)]
-pub mod whisper; 
\ No newline at end of file
+pub mod whisper;
+#[allow(dead_code)]
+pub mod segment;
+#[allow(dead_code)]
+pub mod vocabulary;
+#[allow(dead_code)]
+pub mod worker;
@@ -0,0 +1,167 @@
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+/// Magic number at the start of a ggml Whisper model ("ggml" as a little-endian u32)
+const GGML_MAGIC: u32 = 0x6767_6d6c;
+/// Vocabulary size of English-only models; multilingual models have more tokens
+const ENGLISH_VOCAB_SIZE: i32 = 51864;
+/// Factor the quantization version is multiplied with in the stored file type
+const QUANTIZATION_VERSION_FACTOR: i32 = 1000;
+/// Extension of ggml model files
+const MODEL_EXTENSION: &str = "bin";
+
+/// Description of a ggml Whisper model file
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelInfo {
+    /// Path of the model file
+    pub path: PathBuf,
+    /// File name of the model
+    pub name: String,
+    /// Size of the file in bytes
+    pub size_bytes: u64,
+    /// Model size class: tiny, base, small, medium or large
+    pub model_type: String,
+    /// Weight format, e.g. f16 or q5_0
+    pub quantization: String,
+    /// Whether the model can transcribe languages other than English
+    pub multilingual: bool,
+}
+
+/// Hyperparameters stored in the ggml header after the magic number
+struct ModelHeader {
+    n_vocab: i32,
+    n_audio_layer: i32,
+    ftype: i32,
+}
+
+/// Lists the valid ggml Whisper models in a directory.
+///
+/// Files that are not valid models are skipped.
+///
+/// # Arguments
+///
+/// * `dir` - Directory to scan
+///
+/// # Returns
+///
+/// * `Result<Vec<ModelInfo>, String>` - Models sorted by name, or an error if the
+///   directory can't be read
+pub fn list_models(dir: &Path) -> Result<Vec<ModelInfo>, String> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read models directory {}: {}", dir.display(), e))?;
+
+    let mut models = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(MODEL_EXTENSION) {
+            continue;
+        }
+        match inspect_model(&path) {
+            Ok(model) => models.push(model),
+            Err(err) => println!("Skipping {}: {}", path.display(), err),
+        }
+    }
+
+    models.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(models)
+}
+
+/// Reads and validates the header of a ggml Whisper model.
+///
+/// # Arguments
+///
+/// * `path` - Path of the model file
+///
+/// # Returns
+///
+/// * `Result<ModelInfo, String>` - Description of the model, or an error if the file
+///   is not a ggml Whisper model
+pub fn inspect_model(path: &Path) -> Result<ModelInfo, String> {
+    let mut file = File::open(path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let size_bytes = file
+        .metadata()
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        .len();
+
+    let header = read_header(&mut file)
+        .map_err(|e| format!("{} is not a ggml Whisper model: {}", path.display(), e))?;
+
+    Ok(ModelInfo {
+        path: path.to_path_buf(),
+        name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        size_bytes,
+        model_type: model_type(header.n_audio_layer).to_string(),
+        quantization: quantization(header.ftype % QUANTIZATION_VERSION_FACTOR).to_string(),
+        multilingual: header.n_vocab > ENGLISH_VOCAB_SIZE,
+    })
+}
+
+/// Reads the magic number and the hyperparameters of a ggml model.
+fn read_header(reader: &mut impl Read) -> Result<ModelHeader, String> {
+    let mut read_i32 = || -> Result<i32, String> {
+        let mut bytes = [0u8; 4];
+        reader
+            .read_exact(&mut bytes)
+            .map_err(|_| "file is too short".to_string())?;
+        Ok(i32::from_le_bytes(bytes))
+    };
+
+    if read_i32()? as u32 != GGML_MAGIC {
+        return Err("bad magic number".to_string());
+    }
+
+    // n_vocab, n_audio_ctx, n_audio_state, n_audio_head, n_audio_layer,
+    // n_text_ctx, n_text_state, n_text_head, n_text_layer, n_mels, ftype
+    let mut hparams = [0i32; 11];
+    for value in hparams.iter_mut() {
+        *value = read_i32()?;
+    }
+
+    if hparams.iter().take(10).any(|&value| value <= 0) {
+        return Err("invalid hyperparameters".to_string());
+    }
+
+    Ok(ModelHeader {
+        n_vocab: hparams[0],
+        n_audio_layer: hparams[4],
+        ftype: hparams[10],
+    })
+}
+
+/// Maps the number of encoder layers to the model size class.
+fn model_type(n_audio_layer: i32) -> &'static str {
+    match n_audio_layer {
+        4 => "tiny",
+        6 => "base",
+        12 => "small",
+        24 => "medium",
+        32 => "large",
+        _ => "unknown",
+    }
+}
+
+/// Maps the ggml file type to the name of the weight format.
+fn quantization(ftype: i32) -> &'static str {
+    match ftype {
+        0 => "f32",
+        1 => "f16",
+        2 => "q4_0",
+        3 => "q4_1",
+        7 => "q8_0",
+        8 => "q5_0",
+        9 => "q5_1",
+        10 => "q2_k",
+        11 => "q3_k",
+        12 => "q4_k",
+        13 => "q5_k",
+        14 => "q6_k",
+        _ => "unknown",
+    }
+}
@@ -0,0 +1,1015 @@
+use crate::transcription::segment::{TranscriptSegment, TranscriptToken};
+use crate::transcription::service::TranscriptionTask;
+
+/// Characters that end a sentence
+const SENTENCE_END: [char; 3] = ['.', '!', '?'];
+/// Punctuation that is written without a space before it
+const CLOSING_PUNCTUATION: [char; 7] = [',', '.', '!', '?', ';', ':', '%'];
+
+const EN_MONTHS: [&str; 12] = [
+    "january", "february", "march", "april", "may", "june",
+    "july", "august", "september", "october", "november", "december",
+];
+/// English months that are also common verbs, taken as a date only before an ordinal
+const EN_VERB_MONTHS: [&str; 2] = ["march", "may"];
+const DE_MONTHS: [&str; 12] = [
+    "januar", "februar", "märz", "april", "mai", "juni",
+    "juli", "august", "september", "oktober", "november", "dezember",
+];
+
+/// Spoken units and their symbols, longest phrases first
+const EN_UNITS: [(&str, &str); 26] = [
+    ("kilometers per hour", "km/h"), ("kilometres per hour", "km/h"), ("miles per hour", "mph"),
+    ("degrees celsius", "°C"), ("degrees fahrenheit", "°F"),
+    ("kilometers", "km"), ("kilometres", "km"), ("kilometer", "km"), ("kilometre", "km"),
+    ("centimeters", "cm"), ("centimetres", "cm"), ("millimeters", "mm"), ("millimetres", "mm"),
+    ("meters", "m"), ("metres", "m"), ("kilograms", "kg"), ("kilogram", "kg"),
+    ("grams", "g"), ("degrees", "°"), ("terabytes", "TB"), ("gigabytes", "GB"),
+    ("megabytes", "MB"), ("kilobytes", "KB"), ("milliseconds", "ms"), ("hertz", "Hz"),
+    ("kilohertz", "kHz"),
+];
+const DE_UNITS: [(&str, &str); 16] = [
+    ("kilometer pro stunde", "km/h"), ("stundenkilometer", "km/h"),
+    ("grad celsius", "°C"), ("kilometer", "km"), ("zentimeter", "cm"), ("millimeter", "mm"),
+    ("meter", "m"), ("kilogramm", "kg"), ("kilo", "kg"), ("gramm", "g"), ("grad", "°"),
+    ("terabyte", "TB"), ("gigabyte", "GB"), ("megabyte", "MB"), ("millisekunden", "ms"),
+    ("hertz", "Hz"),
+];
+
+/// Configuration for inverse text normalization of transcribed text
+#[derive(Clone)]
+pub struct NormalizationConfig {
+    /// Whether the text is normalized at all
+    pub enabled: bool,
+    /// Write spoken numbers as digits ("twenty five" -> "25")
+    pub numbers: bool,
+    /// Write dates in written form ("march third" -> "March 3")
+    pub dates: bool,
+    /// Write times in written form ("three thirty pm" -> "3:30 PM")
+    pub times: bool,
+    /// Write amounts with currency symbols ("five dollars" -> "$5")
+    pub currencies: bool,
+    /// Write units and percentages as symbols ("ten percent" -> "10%")
+    pub units: bool,
+    /// Capitalize sentence starts and fix spacing around punctuation
+    pub capitalization: bool,
+    /// Keep the text as spoken in the segment's `spoken_text`
+    pub keep_spoken_form: bool,
+}
+
+impl Default for NormalizationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            numbers: true,
+            dates: true,
+            times: true,
+            currencies: true,
+            units: true,
+            capitalization: true,
+            keep_spoken_form: false,
+        }
+    }
+}
+
+/// Language-specific rules
+#[derive(Clone, Copy, PartialEq)]
+enum RuleSet {
+    English,
+    German,
+    /// Only spacing and capitalization are fixed
+    Other,
+}
+
+impl RuleSet {
+    fn for_language(language: &str) -> Self {
+        match language {
+            "en" => RuleSet::English,
+            "de" => RuleSet::German,
+            _ => RuleSet::Other,
+        }
+    }
+}
+
+/// A number read from spoken words
+struct Number {
+    /// Integer part
+    integer: u64,
+    /// Digits after the decimal separator
+    decimals: String,
+    /// Whether the number was a single digit word such as "one", which usually
+    /// stays a word unless a unit follows
+    single_digit_word: bool,
+}
+
+/// A rewrite of consecutive words into their written form
+struct Rewrite {
+    /// Index of the first rewritten word
+    start: usize,
+    /// Number of rewritten words
+    len: usize,
+    /// Written form, without surrounding whitespace and punctuation
+    text: String,
+}
+
+/// Normalizer rewrites spoken forms in transcribed text into written forms.
+pub struct Normalizer {
+    /// Configuration for the normalizer
+    config: NormalizationConfig,
+}
+
+impl Normalizer {
+    /// Creates a new Normalizer with the specified configuration.
+    pub fn with_config(config: NormalizationConfig) -> Self {
+        Self { config }
+    }
+
+    /// Normalizes a segment in place, keeping its tokens and text in sync.
+    ///
+    /// A rewritten span becomes a single token with the lowest probability and
+    /// the combined time range of the tokens it replaces.
+    ///
+    /// # Arguments
+    ///
+    /// * `segment` - The segment to normalize
+    /// * `starts_sentence` - Whether the segment starts a new sentence
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether the segment ends a sentence
+    pub fn normalize_segment(&self, segment: &mut TranscriptSegment, starts_sentence: bool) -> bool {
+        if !self.config.enabled {
+            return ends_sentence(&segment.text, starts_sentence);
+        }
+        if self.config.keep_spoken_form {
+            segment.spoken_text = Some(segment.text.clone());
+        }
+
+        // Segments without tokens are treated as one token per word
+        let has_tokens = !segment.tokens.is_empty();
+        let words = if has_tokens {
+            segment.take_words()
+        } else {
+            split_words(&segment.text)
+        };
+
+        let texts: Vec<String> = words
+            .iter()
+            .map(|word| word.iter().map(|t| t.text.as_str()).collect())
+            .collect();
+        // Translations are always English
+        let language = match segment.task {
+            TranscriptionTask::Translate => "en",
+            TranscriptionTask::Transcribe => segment.language.as_str(),
+        };
+        let rules = RuleSet::for_language(language);
+        let rewrites = self.find_rewrites(&texts, rules);
+
+        let mut tokens = Vec::new();
+        let mut index = 0;
+        let mut rewrites = rewrites.into_iter().peekable();
+        while index < words.len() {
+            match rewrites.next_if(|r| r.start == index) {
+                Some(rewrite) => {
+                    let span = &words[index..index + rewrite.len];
+                    let first = span.iter().flatten().next();
+                    let last = span.iter().flatten().last();
+                    tokens.push(TranscriptToken {
+                        text: surround(&texts[index], &texts[index + rewrite.len - 1], &rewrite.text),
+                        probability: span.iter().flatten().map(|t| t.probability).fold(1.0, f32::min),
+                        start: first.map_or(segment.start, |t| t.start),
+                        end: last.map_or(segment.end, |t| t.end),
+                    });
+                    index += rewrite.len;
+                }
+                None => {
+                    tokens.extend(words[index].iter().cloned());
+                    index += 1;
+                }
+            }
+        }
+
+        if self.config.capitalization {
+            clean_up(&mut tokens, starts_sentence, rules);
+        }
+
+        if has_tokens {
+            segment.set_tokens(tokens);
+        } else {
+            segment.text = tokens.into_iter().map(|t| t.text).collect();
+        }
+        ends_sentence(&segment.text, starts_sentence)
+    }
+
+    /// Normalizes plain text, e.g. a reference transcript.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text to normalize
+    /// * `language` - Language code selecting the rule set
+    ///
+    /// # Returns
+    ///
+    /// * `String` - The normalized text
+    pub fn normalize(&self, text: &str, language: &str) -> String {
+        if !self.config.enabled {
+            return text.to_string();
+        }
+
+        let mut segment_words = split_words(text);
+        let texts: Vec<String> = segment_words
+            .iter()
+            .map(|word| word[0].text.clone())
+            .collect();
+        let rules = RuleSet::for_language(language);
+        let rewrites = self.find_rewrites(&texts, rules);
+
+        let mut tokens = Vec::new();
+        let mut index = 0;
+        let mut rewrites = rewrites.into_iter().peekable();
+        while index < texts.len() {
+            match rewrites.next_if(|r| r.start == index) {
+                Some(rewrite) => {
+                    tokens.push(TranscriptToken {
+                        text: surround(&texts[index], &texts[index + rewrite.len - 1], &rewrite.text),
+                        probability: 1.0,
+                        start: 0.0,
+                        end: 0.0,
+                    });
+                    index += rewrite.len;
+                }
+                None => {
+                    tokens.append(&mut segment_words[index]);
+                    index += 1;
+                }
+            }
+        }
+
+        if self.config.capitalization {
+            clean_up(&mut tokens, true, rules);
+        }
+        tokens.into_iter().map(|t| t.text).collect()
+    }
+
+    /// Finds the spans of words to rewrite, left to right.
+    fn find_rewrites(&self, words: &[String], rules: RuleSet) -> Vec<Rewrite> {
+        if rules == RuleSet::Other {
+            return Vec::new();
+        }
+
+        let keys: Vec<String> = words.iter().map(|word| word_key(word)).collect();
+        // A span may end at punctuation but never cross it
+        let breaks: Vec<bool> = words
+            .iter()
+            .map(|word| word.trim_end().ends_with(|c: char| c.is_ascii_punctuation() && c != '%'))
+            .collect();
+
+        let mut rewrites = Vec::new();
+        let mut index = 0;
+        while index < words.len() {
+            let end = breaks[index..]
+                .iter()
+                .position(|&b| b)
+                .map_or(words.len(), |offset| index + offset + 1);
+            let phrase = &keys[index..end];
+
+            let rewrite = self
+                .rewrite_date(phrase, rules)
+                .or_else(|| self.rewrite_time(phrase, rules))
+                .or_else(|| self.rewrite_number(phrase, rules));
+
+            match rewrite {
+                Some((len, text)) if len > 0 => {
+                    rewrites.push(Rewrite { start: index, len, text });
+                    index += len;
+                }
+                _ => index += 1,
+            }
+        }
+        rewrites
+    }
+
+    /// Rewrites a date at the start of the phrase.
+    fn rewrite_date(&self, phrase: &[String], rules: RuleSet) -> Option<(usize, String)> {
+        if !self.config.dates {
+            return None;
+        }
+
+        match rules {
+            RuleSet::English => {
+                // "march third [twenty twenty four]" or "the third of march [...]"
+                let (month, day, mut len) = if let Some(month) = month_index(&phrase[0], &EN_MONTHS) {
+                    // "may" and "march" are also verbs ("we march ten miles", "may one day"),
+                    // so only an ordinal makes them a date
+                    let cardinal_day = !EN_VERB_MONTHS.contains(&phrase[0].as_str());
+                    let (day, day_len) = parse_day(&phrase[1..], rules, cardinal_day)?;
+                    (month, day, 1 + day_len)
+                } else if phrase[0] == "the" {
+                    let (day, day_len) = parse_day(&phrase[1..], rules, true)?;
+                    if phrase.get(1 + day_len).map(String::as_str) != Some("of") {
+                        return None;
+                    }
+                    let month = month_index(phrase.get(2 + day_len)?, &EN_MONTHS)?;
+                    (month, day, 3 + day_len)
+                } else {
+                    return None;
+                };
+
+                let mut text = format!("{} {}", capitalize(EN_MONTHS[month]), day);
+                if let Some((year, year_len)) = parse_year(&phrase[len..], rules) {
+                    text.push_str(&format!(", {}", year));
+                    len += year_len;
+                }
+                Some((len, text))
+            }
+            RuleSet::German => {
+                // "dritter märz [zweitausendvierundzwanzig]"
+                let (day, day_len) = parse_day(phrase, rules, false)?;
+                let month = month_index(phrase.get(day_len)?, &DE_MONTHS)?;
+                let mut len = day_len + 1;
+
+                let mut text = format!("{}. {}", day, capitalize(DE_MONTHS[month]));
+                if let Some((year, year_len)) = parse_year(&phrase[len..], rules) {
+                    text.push_str(&format!(" {}", year));
+                    len += year_len;
+                }
+                Some((len, text))
+            }
+            RuleSet::Other => None,
+        }
+    }
+
+    /// Rewrites a time of day at the start of the phrase.
+    fn rewrite_time(&self, phrase: &[String], rules: RuleSet) -> Option<(usize, String)> {
+        if !self.config.times {
+            return None;
+        }
+
+        let (hour, hour_len) = parse_integer(phrase, rules)?;
+        let rest = &phrase[hour_len..];
+
+        match rules {
+            RuleSet::English => {
+                if hour > 12 {
+                    return None;
+                }
+                if rest.first().map(String::as_str) == Some("o'clock") {
+                    return Some((hour_len + 1, format!("{}:00", hour)));
+                }
+                if let Some((meridiem, meridiem_len)) = parse_meridiem(rest) {
+                    return Some((hour_len + meridiem_len, format!("{} {}", hour, meridiem)));
+                }
+                let (minute, minute_len) = parse_minute(rest, rules)?;
+                let (meridiem, meridiem_len) = parse_meridiem(&rest[minute_len..])?;
+                Some((
+                    hour_len + minute_len + meridiem_len,
+                    format!("{}:{:02} {}", hour, minute, meridiem),
+                ))
+            }
+            RuleSet::German => {
+                if hour > 24 || rest.first().map(String::as_str) != Some("uhr") {
+                    return None;
+                }
+                match parse_minute(&rest[1..], rules) {
+                    Some((minute, minute_len)) => Some((
+                        hour_len + 1 + minute_len,
+                        format!("{}:{:02} Uhr", hour, minute),
+                    )),
+                    None => Some((hour_len + 1, format!("{} Uhr", hour))),
+                }
+            }
+            RuleSet::Other => None,
+        }
+    }
+
+    /// Rewrites a number at the start of the phrase, with the currency, unit or
+    /// percent sign that follows it.
+    fn rewrite_number(&self, phrase: &[String], rules: RuleSet) -> Option<(usize, String)> {
+        let (number, len) = parse_number(phrase, rules)?;
+        let rest = &phrase[len..];
+        let written = format_number(&number, rules);
+
+        if self.config.currencies {
+            if let Some((currency_len, text)) = currency(rest, &number, &written, rules) {
+                return Some((len + currency_len, text));
+            }
+        }
+
+        if self.config.units {
+            let percent = match rules {
+                RuleSet::English if rest.first().map(String::as_str) == Some("percent") => Some(1),
+                RuleSet::English if rest.starts_with(&["per".to_string(), "cent".to_string()]) => Some(2),
+                RuleSet::German if rest.first().map(String::as_str) == Some("prozent") => Some(1),
+                _ => None,
+            };
+            if let Some(percent_len) = percent {
+                let separator = if rules == RuleSet::German { " " } else { "" };
+                return Some((len + percent_len, format!("{}{}%", written, separator)));
+            }
+
+            let units: &[(&str, &str)] = if rules == RuleSet::German { &DE_UNITS } else { &EN_UNITS };
+            for (spoken, symbol) in units {
+                let spoken: Vec<&str> = spoken.split(' ').collect();
+                if rest.len() >= spoken.len() && rest.iter().zip(&spoken).all(|(a, b)| a == b) {
+                    let separator = if symbol.starts_with('°') { "" } else { " " };
+                    return Some((len + spoken.len(), format!("{}{}{}", written, separator, symbol)));
+                }
+            }
+        }
+
+        if self.config.numbers && !number.single_digit_word {
+            return Some((len, written));
+        }
+        None
+    }
+}
+
+/// Rewrites an amount followed by a currency name.
+fn currency(rest: &[String], number: &Number, written: &str, rules: RuleSet) -> Option<(usize, String)> {
+    let name = rest.first()?.as_str();
+    match rules {
+        RuleSet::English => {
+            let symbol = match name {
+                "dollar" | "dollars" | "bucks" => "$",
+                "euro" | "euros" => "€",
+                "pound" | "pounds" => "£",
+                _ => return None,
+            };
+            // "five dollars and fifty cents"
+            if number.decimals.is_empty() && rest.get(1).map(String::as_str) == Some("and") {
+                if let Some((cents, cents_len)) = parse_integer(&rest[2..], rules) {
+                    if cents < 100 && matches!(rest.get(2 + cents_len).map(String::as_str), Some("cents" | "cent" | "pence")) {
+                        return Some((3 + cents_len, format!("{}{}.{:02}", symbol, written, cents)));
+                    }
+                }
+            }
+            Some((1, format!("{}{}", symbol, written)))
+        }
+        RuleSet::German => {
+            let symbol = match name {
+                "euro" | "euros" => "€",
+                "dollar" | "dollars" => "$",
+                "pfund" => "£",
+                _ => return None,
+            };
+            Some((1, format!("{} {}", written, symbol)))
+        }
+        RuleSet::Other => None,
+    }
+}
+
+/// Reads a number, including decimals ("three point five") and years spoken in
+/// pairs ("nineteen ninety nine").
+fn parse_number(phrase: &[String], rules: RuleSet) -> Option<(Number, usize)> {
+    let (integer, mut len) = parse_integer(phrase, rules)?;
+    let mut number = Number {
+        integer,
+        decimals: String::new(),
+        single_digit_word: len == 1 && integer < 10 && !is_digits(&phrase[0]),
+    };
+
+    // Two two-digit numbers in a row are read as a year
+    if (10..100).contains(&integer) && !is_digits(&phrase[0]) {
+        if let Some((low, low_len)) = parse_year_half(&phrase[len..], rules) {
+            number.integer = integer * 100 + low;
+            number.single_digit_word = false;
+            return Some((number, len + low_len));
+        }
+    }
+
+    let point = if rules == RuleSet::German { "komma" } else { "point" };
+    if phrase.get(len).map(String::as_str) == Some(point) {
+        let digits: String = phrase[len + 1..]
+            .iter()
+            .map_while(|word| digit_word(word, rules))
+            .map(|digit| char::from(b'0' + digit as u8))
+            .collect();
+        if !digits.is_empty() {
+            len += 1 + digits.len();
+            number.decimals = digits;
+            number.single_digit_word = false;
+        }
+    }
+    Some((number, len))
+}
+
+/// Reads the second half of a year spoken in pairs: "ninety nine" or "oh five".
+fn parse_year_half(phrase: &[String], rules: RuleSet) -> Option<(u64, usize)> {
+    if rules == RuleSet::English && phrase.first().map(String::as_str) == Some("oh") {
+        let digit = digit_word(phrase.get(1)?, rules)?;
+        return Some((digit, 2));
+    }
+    let (value, len) = parse_integer(phrase, rules)?;
+    if (10..100).contains(&value) && !is_digits(&phrase[0]) {
+        Some((value, len))
+    } else {
+        None
+    }
+}
+
+/// Reads a plain integer in digits or words.
+fn parse_integer(phrase: &[String], rules: RuleSet) -> Option<(u64, usize)> {
+    let first = phrase.first()?;
+    if is_digits(first) {
+        return first.parse().ok().map(|value| (value, 1));
+    }
+    match rules {
+        RuleSet::English => parse_english_integer(phrase),
+        RuleSet::German => parse_german_integer(phrase),
+        RuleSet::Other => None,
+    }
+}
+
+/// Kind of an English number word
+#[derive(Clone, Copy, PartialEq)]
+enum NumberWord {
+    Unit(u64),
+    Teen(u64),
+    Tens(u64),
+    Hundred,
+    Scale(u64),
+    And,
+    A,
+}
+
+fn english_number_word(word: &str) -> Option<NumberWord> {
+    const UNITS: [&str; 10] = ["zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine"];
+    const TEENS: [&str; 10] = [
+        "ten", "eleven", "twelve", "thirteen", "fourteen", "fifteen", "sixteen", "seventeen", "eighteen", "nineteen",
+    ];
+    const TENS: [&str; 8] = ["twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety"];
+
+    if let Some(i) = UNITS.iter().position(|&w| w == word) {
+        return Some(NumberWord::Unit(i as u64));
+    }
+    if let Some(i) = TEENS.iter().position(|&w| w == word) {
+        return Some(NumberWord::Teen(10 + i as u64));
+    }
+    if let Some(i) = TENS.iter().position(|&w| w == word) {
+        return Some(NumberWord::Tens(20 + 10 * i as u64));
+    }
+    match word {
+        "hundred" => Some(NumberWord::Hundred),
+        "thousand" => Some(NumberWord::Scale(1_000)),
+        "million" => Some(NumberWord::Scale(1_000_000)),
+        "billion" => Some(NumberWord::Scale(1_000_000_000)),
+        "and" => Some(NumberWord::And),
+        "a" => Some(NumberWord::A),
+        _ => None,
+    }
+}
+
+/// Reads an English integer such as "two thousand and twenty four". Hyphenated
+/// words ("twenty-four") count as one word.
+fn parse_english_integer(phrase: &[String]) -> Option<(u64, usize)> {
+    // Split hyphenated words, remembering which word each part belongs to
+    let parts: Vec<(&str, usize)> = phrase
+        .iter()
+        .enumerate()
+        .flat_map(|(index, word)| word.split('-').map(move |part| (part, index)))
+        .collect();
+    let kind = |i: usize| parts.get(i).and_then(|(part, _)| english_number_word(part));
+
+    let mut total = 0;
+    let mut current = 0;
+    let mut last: Option<NumberWord> = None;
+    let mut consumed = 0;
+
+    for i in 0..parts.len() {
+        let Some(word) = kind(i) else { break };
+        let accepted = match word {
+            NumberWord::Unit(value) => {
+                let ok = matches!(last, None | Some(NumberWord::Tens(_) | NumberWord::Hundred | NumberWord::Scale(_) | NumberWord::And));
+                if ok {
+                    current += value;
+                }
+                ok
+            }
+            NumberWord::Teen(value) | NumberWord::Tens(value) => {
+                let ok = matches!(last, None | Some(NumberWord::Hundred | NumberWord::Scale(_) | NumberWord::And));
+                if ok {
+                    current += value;
+                }
+                ok
+            }
+            NumberWord::Hundred => {
+                let ok = matches!(last, Some(NumberWord::Unit(_) | NumberWord::Teen(_) | NumberWord::A));
+                if ok {
+                    current *= 100;
+                }
+                ok
+            }
+            NumberWord::Scale(scale) => {
+                let ok = matches!(
+                    last,
+                    Some(NumberWord::Unit(_) | NumberWord::Teen(_) | NumberWord::Tens(_) | NumberWord::Hundred | NumberWord::A)
+                );
+                if ok {
+                    total += current * scale;
+                    current = 0;
+                }
+                ok
+            }
+            // "and" only joins number words: "one hundred and five"
+            NumberWord::And => {
+                matches!(last, Some(NumberWord::Hundred | NumberWord::Scale(_)))
+                    && matches!(kind(i + 1), Some(NumberWord::Unit(_) | NumberWord::Teen(_) | NumberWord::Tens(_)))
+            }
+            // "a" only counts as one before a scale: "a hundred", "a thousand"
+            NumberWord::A => {
+                let ok = last.is_none() && matches!(kind(i + 1), Some(NumberWord::Hundred | NumberWord::Scale(_)));
+                if ok {
+                    current = 1;
+                }
+                ok
+            }
+        };
+        if !accepted {
+            break;
+        }
+        last = Some(word);
+        consumed = i + 1;
+    }
+
+    // "zero" on its own is a number, "and" and "a" on their own are not
+    if consumed == 0 || matches!(last, Some(NumberWord::And | NumberWord::A)) {
+        return None;
+    }
+
+    // The match has to end at a word boundary
+    let word_index = parts[consumed - 1].1;
+    if parts.get(consumed).is_some_and(|&(_, index)| index == word_index) {
+        return None;
+    }
+    Some((total + current, word_index + 1))
+}
+
+/// Reads a German integer. German writes numbers below a million as one word
+/// ("zweitausendvierundzwanzig"); larger scales are separate words.
+fn parse_german_integer(phrase: &[String]) -> Option<(u64, usize)> {
+    let mut total = 0;
+    let mut len = 0;
+
+    while let Some(value) = phrase.get(len).and_then(|word| german_compound(word)) {
+        let scale = match phrase.get(len + 1).map(String::as_str) {
+            Some("million" | "millionen") => 1_000_000,
+            Some("milliarde" | "milliarden") => 1_000_000_000,
+            _ => {
+                total += value;
+                len += 1;
+                break;
+            }
+        };
+        total += value * scale;
+        len += 2;
+    }
+
+    (len > 0).then_some((total, len))
+}
+
+/// Reads a German number word below a million, e.g. "dreihundertfünfundzwanzig".
+fn german_compound(word: &str) -> Option<u64> {
+    if word.is_empty() {
+        return None;
+    }
+    if let Some((left, right)) = word.split_once("tausend") {
+        let thousands = if left.is_empty() { 1 } else { german_below_thousand(left)? };
+        let rest = if right.is_empty() { 0 } else { german_below_thousand(right)? };
+        return Some(thousands * 1000 + rest);
+    }
+    german_below_thousand(word)
+}
+
+fn german_below_thousand(word: &str) -> Option<u64> {
+    if let Some((left, right)) = word.split_once("hundert") {
+        // "neunzehnhundert" is how years before 2000 are spoken
+        let hundreds = if left.is_empty() { 1 } else { german_below_hundred(left)? };
+        let rest = if right.is_empty() { 0 } else { german_below_hundred(right)? };
+        return Some(hundreds * 100 + rest);
+    }
+    german_below_hundred(word)
+}
+
+fn german_below_hundred(word: &str) -> Option<u64> {
+    const SMALL: [(&str, u64); 23] = [
+        ("null", 0), ("eins", 1), ("ein", 1), ("eine", 1), ("zwei", 2), ("drei", 3), ("vier", 4),
+        ("fünf", 5), ("sechs", 6), ("sieben", 7), ("acht", 8), ("neun", 9), ("zehn", 10),
+        ("elf", 11), ("zwölf", 12), ("dreizehn", 13), ("vierzehn", 14), ("fünfzehn", 15),
+        ("sechzehn", 16), ("siebzehn", 17), ("achtzehn", 18), ("neunzehn", 19), ("zwo", 2),
+    ];
+    const TENS: [(&str, u64); 8] = [
+        ("zwanzig", 20), ("dreißig", 30), ("vierzig", 40), ("fünfzig", 50),
+        ("sechzig", 60), ("siebzig", 70), ("achtzig", 80), ("neunzig", 90),
+    ];
+    let lookup = |table: &[(&str, u64)], word: &str| table.iter().find(|(w, _)| *w == word).map(|(_, v)| *v);
+
+    if let Some(value) = lookup(&SMALL, word).or_else(|| lookup(&TENS, word)) {
+        return Some(value);
+    }
+    // "fünfundzwanzig"
+    let (unit, tens) = word.split_once("und")?;
+    let unit = lookup(&SMALL, unit).filter(|&u| (1..10).contains(&u))?;
+    Some(lookup(&TENS, tens)? + unit)
+}
+
+/// Reads a single digit word, e.g. after "point".
+fn digit_word(word: &str, rules: RuleSet) -> Option<u64> {
+    if word.len() == 1 && is_digits(word) {
+        return word.parse().ok();
+    }
+    match rules {
+        RuleSet::English => match english_number_word(word) {
+            Some(NumberWord::Unit(value)) => Some(value),
+            _ if word == "oh" => Some(0),
+            _ => None,
+        },
+        RuleSet::German => german_below_hundred(word).filter(|&value| value < 10),
+        RuleSet::Other => None,
+    }
+}
+
+/// Reads the day of a date: an ordinal ("third", "dritten", "3rd") or, if
+/// `cardinal` is set, a number.
+fn parse_day(phrase: &[String], rules: RuleSet, cardinal: bool) -> Option<(u64, usize)> {
+    let (day, len) = parse_ordinal(phrase, rules).or_else(|| match rules {
+        // German days are always ordinals; English also says "june three"
+        RuleSet::English if cardinal => parse_integer(phrase, rules),
+        _ => None,
+    })?;
+    (1..=31).contains(&day).then_some((day, len))
+}
+
+/// Reads an ordinal number.
+fn parse_ordinal(phrase: &[String], rules: RuleSet) -> Option<(u64, usize)> {
+    let first = phrase.first()?;
+
+    // "3rd", "21st", "3."
+    let digits: String = first.chars().take_while(char::is_ascii_digit).collect();
+    if !digits.is_empty() {
+        let suffix = &first[digits.len()..];
+        let is_ordinal = match rules {
+            RuleSet::English => matches!(suffix, "st" | "nd" | "rd" | "th"),
+            _ => false,
+        };
+        return if is_ordinal { digits.parse().ok().map(|day| (day, 1)) } else { None };
+    }
+
+    match rules {
+        RuleSet::English => {
+            // "twenty first" or "twenty-first"
+            let (tens, tens_len, word) = match first.split_once('-') {
+                Some((tens, unit)) => (english_tens(tens)?, 1, unit),
+                None => match english_tens(first) {
+                    Some(tens) => (tens, 2, phrase.get(1).map(String::as_str).unwrap_or_default()),
+                    None => (0, 1, first.as_str()),
+                },
+            };
+            let value = english_ordinal(word)?;
+            if tens > 0 && value >= 10 {
+                return None;
+            }
+            Some((tens + value, tens_len))
+        }
+        RuleSet::German => {
+            // "vierten", "zwanzigsten"; a few ordinals have their own stem: "dritten"
+            let irregular = [("erst", 1), ("dritt", 3), ("siebt", 7), ("acht", 8)];
+            ["sten", "ster", "stes", "ste", "ten", "ter", "tes", "te", "en", "er", "es", "e"]
+                .iter()
+                .find_map(|suffix| {
+                    let stem = first.strip_suffix(suffix)?;
+                    irregular
+                        .iter()
+                        .find(|(w, _)| *w == stem)
+                        .map(|(_, v)| *v)
+                        .or_else(|| suffix.starts_with(['s', 't']).then(|| german_below_hundred(stem)).flatten())
+                })
+                .map(|value| (value, 1))
+        }
+        RuleSet::Other => None,
+    }
+}
+
+fn english_tens(word: &str) -> Option<u64> {
+    match english_number_word(word) {
+        Some(NumberWord::Tens(value)) => Some(value),
+        _ => None,
+    }
+}
+
+fn english_ordinal(word: &str) -> Option<u64> {
+    const ORDINALS: [&str; 19] = [
+        "first", "second", "third", "fourth", "fifth", "sixth", "seventh", "eighth", "ninth", "tenth",
+        "eleventh", "twelfth", "thirteenth", "fourteenth", "fifteenth", "sixteenth", "seventeenth",
+        "eighteenth", "nineteenth",
+    ];
+    if let Some(i) = ORDINALS.iter().position(|&w| w == word) {
+        return Some(i as u64 + 1);
+    }
+    match word {
+        "twentieth" => Some(20),
+        "thirtieth" => Some(30),
+        _ => None,
+    }
+}
+
+/// Reads a year after a date.
+fn parse_year(phrase: &[String], rules: RuleSet) -> Option<(u64, usize)> {
+    let (number, len) = parse_number(phrase, rules)?;
+    (number.decimals.is_empty() && (1000..3000).contains(&number.integer)).then_some((number.integer, len))
+}
+
+/// Reads the minutes of a time: "thirty", "oh five" or "05".
+fn parse_minute(phrase: &[String], rules: RuleSet) -> Option<(u64, usize)> {
+    let (minute, len) = if rules == RuleSet::English && phrase.first().map(String::as_str) == Some("oh") {
+        (digit_word(phrase.get(1)?, rules)?, 2)
+    } else {
+        parse_integer(phrase, rules)?
+    };
+    (minute < 60).then_some((minute, len))
+}
+
+/// Reads "am", "pm", "a.m." or "a m".
+fn parse_meridiem(phrase: &[String]) -> Option<(&'static str, usize)> {
+    let first = phrase.first()?.replace('.', "");
+    let meridiem = |letter: &str| match letter {
+        "a" => Some("AM"),
+        "p" => Some("PM"),
+        _ => None,
+    };
+    match first.as_str() {
+        "am" => Some(("AM", 1)),
+        "pm" => Some(("PM", 1)),
+        letter if phrase.get(1).map(|w| w.replace('.', "")).as_deref() == Some("m") => {
+            meridiem(letter).map(|m| (m, 2))
+        }
+        _ => None,
+    }
+}
+
+/// Writes a number with the separators of the language.
+fn format_number(number: &Number, rules: RuleSet) -> String {
+    let (group, decimal) = if rules == RuleSet::German { ('.', ',') } else { (',', '.') };
+
+    let digits = number.integer.to_string();
+    // Years and other four-digit numbers are written without a separator
+    let mut integer = String::new();
+    if number.integer >= 10_000 {
+        for (i, c) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i) % 3 == 0 {
+                integer.push(group);
+            }
+            integer.push(c);
+        }
+    } else {
+        integer = digits;
+    }
+
+    if number.decimals.is_empty() {
+        integer
+    } else {
+        format!("{}{}{}", integer, decimal, number.decimals)
+    }
+}
+
+/// Fixes spacing around punctuation and capitalizes sentence starts, and "I" in English.
+fn clean_up(tokens: &mut [TranscriptToken], starts_sentence: bool, rules: RuleSet) {
+    let mut sentence_start = starts_sentence;
+    let mut first = true;
+
+    for token in tokens.iter_mut() {
+        // Collapse runs of whitespace; no space before closing punctuation
+        let trimmed = token.text.trim_start();
+        let had_space = trimmed.len() != token.text.len();
+        if had_space {
+            let closes = !first && trimmed.starts_with(CLOSING_PUNCTUATION) && !trimmed.starts_with("...");
+            token.text = if closes { trimmed.to_string() } else { format!(" {}", trimmed) };
+        }
+        first = false;
+
+        // A sentence starting with a number keeps the next word lowercase
+        if sentence_start {
+            if let Some(index) = token.text.find(char::is_alphanumeric) {
+                let (head, tail) = token.text.split_at(index);
+                token.text = format!("{}{}", head, capitalize(tail));
+                sentence_start = false;
+            }
+        }
+
+        // Standalone "i", "i'm", "i've"...
+        let core = token.text.trim();
+        if rules == RuleSet::English && (core == "i" || core.starts_with("i'")) {
+            token.text = token.text.replacen('i', "I", 1);
+        }
+
+        if token.text.trim_end().ends_with(SENTENCE_END) {
+            sentence_start = true;
+        }
+    }
+}
+
+/// Whether text ends a sentence; empty text leaves the state unchanged.
+fn ends_sentence(text: &str, starts_sentence: bool) -> bool {
+    let text = text.trim_end();
+    if text.is_empty() {
+        return starts_sentence;
+    }
+    text.ends_with(SENTENCE_END)
+}
+
+/// Lowercased word without surrounding punctuation, used to match spoken forms.
+fn word_key(word: &str) -> String {
+    word.trim()
+        .trim_matches(|c: char| !c.is_alphanumeric() && c != '\'')
+        .trim_matches('\'')
+        .to_lowercase()
+}
+
+/// Puts the whitespace and punctuation around the original words around a rewrite.
+fn surround(first: &str, last: &str, text: &str) -> String {
+    let prefix_end = first.find(char::is_alphanumeric).unwrap_or(first.len());
+    let suffix_start = last
+        .char_indices()
+        .rev()
+        .find(|(_, c)| c.is_alphanumeric())
+        .map_or(0, |(i, c)| i + c.len_utf8());
+    let suffix = &last[suffix_start..];
+    // A trailing "." of "a.m." is part of the abbreviation, not the sentence
+    let suffix = if text.ends_with('M') && suffix == "." && last.contains(".m.") { "" } else { suffix };
+    format!("{}{}{}", &first[..prefix_end], text, suffix)
+}
+
+/// Uppercases the first character.
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn month_index(word: &str, months: &[&str; 12]) -> Option<usize> {
+    months.iter().position(|&month| month == word)
+}
+
+fn is_digits(word: &str) -> bool {
+    !word.is_empty() && word.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Splits text into single-token words, each carrying the whitespace before it.
+fn split_words(text: &str) -> Vec<Vec<TranscriptToken>> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    for c in text.chars() {
+        if c.is_whitespace() && current.chars().any(|c| !c.is_whitespace()) {
+            words.push(std::mem::take(&mut current));
+        }
+        current.push(c);
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+
+    words
+        .into_iter()
+        .map(|text| vec![TranscriptToken { text, probability: 1.0, start: 0.0, end: 0.0 }])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalizer() -> Normalizer {
+        Normalizer::with_config(NormalizationConfig { enabled: true, ..NormalizationConfig::default() })
+    }
+
+    #[test]
+    fn dates_are_written_out() {
+        let normalizer = normalizer();
+        assert_eq!(normalizer.normalize("the meeting is on june three", "en"), "The meeting is on June 3");
+        assert_eq!(normalizer.normalize("march third twenty twenty four", "en"), "March 3, 2024");
+        assert_eq!(normalizer.normalize("am dritten märz", "de"), "Am 3. März");
+    }
+
+    #[test]
+    fn months_that_are_verbs_need_an_ordinal() {
+        let normalizer = normalizer();
+        assert_eq!(normalizer.normalize("we march ten miles", "en"), "We march 10 miles");
+        assert_eq!(normalizer.normalize("you may one day", "en"), "You may one day");
+        assert_eq!(normalizer.normalize("on may first", "en"), "On May 1");
+    }
+
+    #[test]
+    fn numbers_units_and_currencies_are_written_out() {
+        let normalizer = normalizer();
+        assert_eq!(normalizer.normalize("twenty five percent", "en"), "25%");
+        assert_eq!(normalizer.normalize("it costs five dollars", "en"), "It costs $5");
+        assert_eq!(normalizer.normalize("at three thirty pm", "en"), "At 3:30 PM");
+    }
+
+    #[test]
+    fn text_is_left_alone_when_disabled() {
+        let normalizer = Normalizer::with_config(NormalizationConfig::default());
+        assert_eq!(normalizer.normalize("twenty five percent", "en"), "twenty five percent");
+    }
+}
@@ -0,0 +1,464 @@
+use std::collections::VecDeque;
+use std::sync::mpsc;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::transcription::worker::TranscriptionJob;
+
+/// Weight of the newest measurement in the moving average of the real-time factor
+const REAL_TIME_FACTOR_SMOOTHING: f32 = 0.3;
+
+/// What the queue does when transcription can't keep up with the audio
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverloadPolicy {
+    /// Drop the oldest windows, keeping the transcript close to live
+    #[default]
+    DropOldest,
+    /// Merge queued windows so fewer, longer inferences catch up
+    Merge,
+    /// Switch to a faster model for the rest of the session; windows that still
+    /// miss their deadline are dropped
+    FallbackModel,
+}
+
+/// Configuration for the transcription queue
+#[derive(Clone)]
+pub struct QueueConfig {
+    /// Number of final windows that can wait for the worker
+    pub capacity: usize,
+    /// Seconds a window may wait in the queue before the overload policy applies to it
+    pub deadline_seconds: f32,
+    /// What to do with windows past their deadline or beyond the capacity
+    pub policy: OverloadPolicy,
+    /// Real-time factor (processing time / audio duration) above which the
+    /// fallback model is loaded
+    pub max_real_time_factor: f32,
+    /// Model switched to under the fallback_model policy
+    pub fallback_model_path: Option<String>,
+    /// Longest window, in seconds, that merging may produce
+    pub max_merge_seconds: f32,
+    /// Sample rate of the queued audio
+    pub sample_rate: u32,
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 4,
+            deadline_seconds: 10.0,
+            policy: OverloadPolicy::default(),
+            max_real_time_factor: 1.0,
+            fallback_model_path: None,
+            max_merge_seconds: 30.0,
+            sample_rate: 16000,
+        }
+    }
+}
+
+/// Kind of decision the queue took
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueAction {
+    /// Windows were dropped without being transcribed
+    Dropped,
+    /// Windows were merged into one
+    Merged,
+    /// The fallback model is being loaded
+    SwitchedModel,
+}
+
+/// A decision taken because transcription fell behind, reported to the UI
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueDecision {
+    /// What was done
+    pub action: QueueAction,
+    /// Ids of the affected windows
+    pub segment_ids: Vec<u64>,
+    /// Seconds of audio affected
+    pub audio_seconds: f32,
+    /// Windows waiting after the decision
+    pub queued: usize,
+    /// Current real-time factor, None before the first window is transcribed
+    pub real_time_factor: Option<f32>,
+    /// Why the decision was taken
+    pub reason: String,
+}
+
+/// A queued job with the time it has to start by
+struct QueuedJob {
+    job: TranscriptionJob,
+    deadline: Instant,
+    /// Ids of all windows in the job, more than one once merged
+    segment_ids: Vec<u64>,
+}
+
+/// Mutable state of the queue
+struct QueueState {
+    /// Jobs in order of arrival
+    jobs: VecDeque<QueuedJob>,
+    /// Whether no more jobs will be pushed
+    closed: bool,
+    /// Moving average of the real-time factor
+    real_time_factor: Option<f32>,
+    /// Whether the fallback model was switched to during this session
+    switched_model: bool,
+}
+
+/// JobQueue hands windows from the audio loop to the transcription worker.
+///
+/// Pushing never waits, so audio intake is never held up by slow inference.
+/// Instead, windows that wait too long or overflow the queue are dropped or
+/// merged according to the overload policy, and every such decision is reported.
+pub struct JobQueue {
+    /// Configuration for the queue
+    config: QueueConfig,
+    /// Queued jobs and measurements
+    state: Mutex<QueueState>,
+    /// Signalled when a job is pushed or the queue is closed
+    available: Condvar,
+    /// Receives every decision taken
+    decisions: mpsc::Sender<QueueDecision>,
+}
+
+impl JobQueue {
+    /// Creates a new JobQueue with the specified configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Capacity, deadline and overload policy
+    /// * `decisions` - Channel receiving every decision the queue takes
+    pub fn with_config(config: QueueConfig, decisions: mpsc::Sender<QueueDecision>) -> Self {
+        Self {
+            config,
+            state: Mutex::new(QueueState {
+                jobs: VecDeque::new(),
+                closed: false,
+                real_time_factor: None,
+                switched_model: false,
+            }),
+            available: Condvar::new(),
+            decisions,
+        }
+    }
+
+    /// Queues a window for transcription.
+    ///
+    /// An interim job is only queued if nothing else is waiting, so interim results
+    /// never hold up final ones. A final job replaces waiting interim jobs.
+    ///
+    /// # Arguments
+    ///
+    /// * `job` - The window to transcribe
+    ///
+    /// # Returns
+    ///
+    /// * `Result<bool, String>` - Whether the job was queued, or an error if the queue is closed
+    pub fn push(&self, job: TranscriptionJob) -> Result<bool, String> {
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            return Err("Transcription queue is closed".to_string());
+        }
+
+        if !job.is_final {
+            if !state.jobs.is_empty() {
+                return Ok(false);
+            }
+        } else {
+            // The final result supersedes any interim hypothesis still waiting
+            state.jobs.retain(|queued| queued.job.is_final);
+        }
+
+        let deadline = Instant::now() + Duration::from_secs_f32(self.config.deadline_seconds.max(0.0));
+        let segment_ids = vec![job.segment_id];
+        state.jobs.push_back(QueuedJob { job, deadline, segment_ids });
+
+        let capacity = self.config.capacity.max(1);
+        while state.jobs.len() > capacity {
+            let reason = format!("queue holds more than {} windows", capacity);
+            // Merge the oldest neighbours that still fit into one window
+            let merged = self.config.policy == OverloadPolicy::Merge
+                && (0..state.jobs.len() - 1).any(|index| self.merge(&mut state, index, 2, &reason));
+            if !merged {
+                self.drop_front(&mut state, 1, &reason);
+            }
+        }
+
+        self.available.notify_one();
+        Ok(true)
+    }
+
+    /// Waits for the next job, applying the overload policy to jobs past their deadline.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<TranscriptionJob>` - The next job, or None once the queue is closed and empty
+    pub fn pop(&self) -> Option<TranscriptionJob> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if !state.jobs.is_empty() {
+                break;
+            }
+            if state.closed {
+                return None;
+            }
+            state = self.available.wait(state).unwrap();
+        }
+
+        // Jobs are queued in order, so the expired ones are at the front
+        let now = Instant::now();
+        let expired = state.jobs.iter().take_while(|queued| queued.deadline <= now).count();
+        if expired > 0 {
+            let reason = format!("waited longer than {:.1}s", self.config.deadline_seconds);
+            match self.config.policy {
+                OverloadPolicy::Merge => {
+                    // The expired windows and the one after them become one inference;
+                    // whatever doesn't fit is dropped
+                    if !self.merge(&mut state, 0, expired + 1, &reason) {
+                        let count = expired.min(state.jobs.len() - 1);
+                        self.drop_front(&mut state, count, &reason);
+                    }
+                }
+                // Always keep the newest window, so the transcript catches up with live audio
+                OverloadPolicy::DropOldest | OverloadPolicy::FallbackModel => {
+                    let count = expired.min(state.jobs.len() - 1);
+                    self.drop_front(&mut state, count, &reason);
+                }
+            }
+        }
+
+        state.jobs.pop_front().map(|queued| queued.job)
+    }
+
+    /// Stops accepting jobs; the worker finishes those already queued.
+    pub fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.available.notify_all();
+    }
+
+    /// Records how long a window took to transcribe.
+    ///
+    /// # Arguments
+    ///
+    /// * `audio_seconds` - Duration of the transcribed audio
+    /// * `processing` - Time the transcription took
+    ///
+    /// # Returns
+    ///
+    /// * `Option<String>` - The model to switch to, if the real-time factor calls for
+    ///   the fallback model; returned at most once per session
+    pub fn record(&self, audio_seconds: f32, processing: Duration) -> Option<String> {
+        if audio_seconds <= 0.0 {
+            return None;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let measured = processing.as_secs_f32() / audio_seconds;
+        let real_time_factor = match state.real_time_factor {
+            Some(average) => average + REAL_TIME_FACTOR_SMOOTHING * (measured - average),
+            None => measured,
+        };
+        state.real_time_factor = Some(real_time_factor);
+
+        if self.config.policy != OverloadPolicy::FallbackModel
+            || state.switched_model
+            || real_time_factor <= self.config.max_real_time_factor
+        {
+            return None;
+        }
+        let model_path = self.config.fallback_model_path.clone()?;
+
+        state.switched_model = true;
+        self.report(
+            &state,
+            QueueDecision {
+                action: QueueAction::SwitchedModel,
+                segment_ids: Vec::new(),
+                audio_seconds: 0.0,
+                queued: 0,
+                real_time_factor: None,
+                reason: format!(
+                    "real-time factor {:.2} exceeds {:.2}, switching to {}",
+                    real_time_factor, self.config.max_real_time_factor, model_path
+                ),
+            },
+        );
+        Some(model_path)
+    }
+
+    /// Returns the moving average of the real-time factor.
+    pub fn real_time_factor(&self) -> Option<f32> {
+        self.state.lock().unwrap().real_time_factor
+    }
+
+    /// Merges up to `max_count` jobs starting at `index` into one, as many as are
+    /// final and fit into `max_merge_seconds`.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether at least two jobs were merged
+    fn merge(&self, state: &mut QueueState, index: usize, max_count: usize, reason: &str) -> bool {
+        let max_samples = (self.config.max_merge_seconds * self.config.sample_rate as f32) as usize;
+        let mut count = 0;
+        let mut samples = 0;
+        for queued in state.jobs.iter().skip(index).take(max_count) {
+            if !queued.job.is_final || samples + queued.job.samples.len() > max_samples {
+                break;
+            }
+            samples += queued.job.samples.len();
+            count += 1;
+        }
+        if count < 2 {
+            return false;
+        }
+
+        let mut jobs = state.jobs.drain(index..index + count);
+        let mut merged = jobs.next().expect("at least two jobs to merge");
+        merged.job.samples.reserve(samples - merged.job.samples.len());
+        for queued in jobs {
+            merged.job.samples.extend(queued.job.samples);
+            // The merged window replaces the interim result of the newest one
+            merged.job.segment_id = queued.job.segment_id;
+            merged.deadline = merged.deadline.max(queued.deadline);
+            merged.segment_ids.extend(queued.segment_ids);
+        }
+
+        let decision = QueueDecision {
+            action: QueueAction::Merged,
+            segment_ids: merged.segment_ids.clone(),
+            audio_seconds: samples as f32 / self.config.sample_rate as f32,
+            queued: 0,
+            real_time_factor: None,
+            reason: reason.to_string(),
+        };
+        state.jobs.insert(index, merged);
+        self.report(state, decision);
+        true
+    }
+
+    /// Drops the first `count` jobs.
+    fn drop_front(&self, state: &mut QueueState, count: usize, reason: &str) {
+        if count == 0 {
+            return;
+        }
+
+        let dropped: Vec<QueuedJob> = state.jobs.drain(..count.min(state.jobs.len())).collect();
+        let samples: usize = dropped.iter().map(|queued| queued.job.samples.len()).sum();
+        self.report(
+            state,
+            QueueDecision {
+                action: QueueAction::Dropped,
+                segment_ids: dropped.iter().flat_map(|queued| queued.segment_ids.iter().copied()).collect(),
+                audio_seconds: samples as f32 / self.config.sample_rate as f32,
+                queued: 0,
+                real_time_factor: None,
+                reason: reason.to_string(),
+            },
+        );
+    }
+
+    /// Logs a decision and sends it on, filling in the queue length and real-time factor.
+    fn report(&self, state: &QueueState, mut decision: QueueDecision) {
+        decision.queued = state.jobs.len();
+        decision.real_time_factor = state.real_time_factor;
+        println!(
+            "Transcription queue {:?} windows {:?} ({:.1}s): {}",
+            decision.action, decision.segment_ids, decision.audio_seconds, decision.reason
+        );
+        // The receiver is gone once the session's events are no longer forwarded
+        let _ = self.decisions.send(decision);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(segment_id: u64, seconds: f32, is_final: bool) -> TranscriptionJob {
+        TranscriptionJob {
+            samples: vec![segment_id as f32; (seconds * 16000.0) as usize],
+            window_start: 0.0,
+            segment_id,
+            is_final,
+        }
+    }
+
+    fn queue(policy: OverloadPolicy, deadline_seconds: f32) -> (JobQueue, mpsc::Receiver<QueueDecision>) {
+        let (decisions, receiver) = mpsc::channel();
+        let config = QueueConfig { capacity: 2, deadline_seconds, policy, max_merge_seconds: 3.0, ..QueueConfig::default() };
+        (JobQueue::with_config(config, decisions), receiver)
+    }
+
+    #[test]
+    fn overflow_drops_the_oldest_window() {
+        let (queue, decisions) = queue(OverloadPolicy::DropOldest, 10.0);
+        for id in 0..3 {
+            assert!(queue.push(job(id, 1.0, true)).unwrap());
+        }
+
+        let decision = decisions.try_recv().unwrap();
+        assert_eq!(decision.action, QueueAction::Dropped);
+        assert_eq!(decision.segment_ids, [0]);
+        assert_eq!(decision.audio_seconds, 1.0);
+        assert_eq!(decision.queued, 2);
+        assert_eq!(queue.pop().unwrap().segment_id, 1);
+        assert_eq!(queue.pop().unwrap().segment_id, 2);
+    }
+
+    #[test]
+    fn overflow_merges_windows_under_the_merge_policy() {
+        let (queue, decisions) = queue(OverloadPolicy::Merge, 10.0);
+        for id in 0..3 {
+            queue.push(job(id, 1.0, true)).unwrap();
+        }
+
+        let decision = decisions.try_recv().unwrap();
+        assert_eq!(decision.action, QueueAction::Merged);
+        assert_eq!(decision.segment_ids, [0, 1]);
+
+        // The merged window carries the audio of both and the id of the newest
+        let merged = queue.pop().unwrap();
+        assert_eq!(merged.segment_id, 1);
+        assert_eq!(merged.samples.len(), 32000);
+        assert_eq!(merged.samples[0], 0.0);
+        assert_eq!(merged.samples[16000], 1.0);
+        assert_eq!(queue.pop().unwrap().segment_id, 2);
+    }
+
+    #[test]
+    fn windows_too_long_to_merge_are_dropped() {
+        let (queue, decisions) = queue(OverloadPolicy::Merge, 10.0);
+        for id in 0..3 {
+            queue.push(job(id, 2.0, true)).unwrap();
+        }
+
+        assert_eq!(decisions.try_recv().unwrap().action, QueueAction::Dropped);
+        assert_eq!(queue.pop().unwrap().segment_id, 1);
+    }
+
+    #[test]
+    fn expired_windows_are_dropped_but_the_newest_is_kept() {
+        let (queue, decisions) = queue(OverloadPolicy::DropOldest, 0.0);
+        queue.push(job(0, 1.0, true)).unwrap();
+        queue.push(job(1, 1.0, true)).unwrap();
+
+        assert_eq!(queue.pop().unwrap().segment_id, 1);
+        assert_eq!(decisions.try_recv().unwrap().segment_ids, [0]);
+    }
+
+    #[test]
+    fn interim_windows_only_run_when_nothing_else_waits() {
+        let (queue, _decisions) = queue(OverloadPolicy::DropOldest, 10.0);
+        assert!(queue.push(job(0, 1.0, false)).unwrap());
+        assert!(!queue.push(job(0, 1.0, false)).unwrap());
+
+        // The final result of the window replaces its waiting interim job
+        queue.push(job(0, 1.0, true)).unwrap();
+        queue.close();
+        assert!(queue.pop().unwrap().is_final);
+        assert!(queue.pop().is_none());
+        assert!(queue.push(job(1, 1.0, true)).is_err());
+    }
+}
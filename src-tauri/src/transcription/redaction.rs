@@ -0,0 +1,536 @@
+use std::ops::Range;
+
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+
+use crate::transcription::segment::{TranscriptSegment, TranscriptToken};
+
+/// Kind of personal information that was redacted
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RedactionKind {
+    Email,
+    Phone,
+    CreditCard,
+    Iban,
+    /// A user-defined term
+    Term,
+}
+
+impl RedactionKind {
+    /// Placeholder written in place of the redacted text.
+    pub fn placeholder(&self) -> &'static str {
+        match self {
+            RedactionKind::Email => "[EMAIL]",
+            RedactionKind::Phone => "[PHONE]",
+            RedactionKind::CreditCard => "[CREDIT_CARD]",
+            RedactionKind::Iban => "[IBAN]",
+            RedactionKind::Term => "[REDACTED]",
+        }
+    }
+}
+
+/// A redacted span of a segment. The redacted text itself is not kept.
+#[derive(Debug, Clone, Serialize)]
+pub struct Redaction {
+    /// What was redacted
+    pub kind: RedactionKind,
+    /// Byte offset of the placeholder in the segment text
+    pub start: usize,
+    /// Byte offset after the placeholder in the segment text
+    pub end: usize,
+    /// Start of the redacted speech in seconds
+    pub start_time: f32,
+    /// End of the redacted speech in seconds
+    pub end_time: f32,
+}
+
+/// Finds one kind of personal information in text.
+pub trait Detector: Send {
+    /// Returns the kind of information the detector finds.
+    fn kind(&self) -> RedactionKind;
+
+    /// Finds all occurrences in the text.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text to search
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Range<usize>>` - Byte ranges of the occurrences, in order
+    fn find(&self, text: &str) -> Vec<Range<usize>>;
+}
+
+/// Detector matching a regular expression, with an optional check of each match
+/// such as a checksum.
+pub struct PatternDetector {
+    /// Kind of information the pattern matches
+    kind: RedactionKind,
+    /// Pattern matching candidates
+    regex: Regex,
+    /// Confirms a candidate; false discards it
+    validate: fn(&str) -> bool,
+}
+
+impl PatternDetector {
+    /// Creates a detector from a pattern.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - Kind of information the pattern matches
+    /// * `pattern` - Regular expression matching candidates
+    /// * `validate` - Confirms a candidate, e.g. by its checksum
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, String>` - The detector, or an error message if the pattern is invalid
+    pub fn new(kind: RedactionKind, pattern: &str, validate: fn(&str) -> bool) -> Result<Self, String> {
+        let regex = Regex::new(pattern).map_err(|e| format!("Invalid redaction pattern: {}", e))?;
+        Ok(Self { kind, regex, validate })
+    }
+
+    /// Detects email addresses.
+    pub fn email() -> Self {
+        Self::new(RedactionKind::Email, r"(?i)\b[a-z0-9._%+-]+@[a-z0-9-]+(?:\.[a-z0-9-]+)*\.[a-z]{2,}\b", |_| true)
+            .expect("valid email pattern")
+    }
+
+    /// Detects phone numbers of 7 to 15 digits: starting with a country code or a
+    /// trunk prefix ("+49 30 1234567", "030 1234567"), or grouped the North American
+    /// way ("(555) 123-4567", "555-123-4567"). Other digit runs such as amounts or
+    /// years are left alone.
+    pub fn phone() -> Self {
+        let pattern = concat!(
+            r"(?:\+|\b0)\d[\d \-/()]{5,}\d\b",
+            r"|\(\d{3}\) ?\d{3}[-. ]\d{4}\b",
+            r"|\b\d{3}[-. ]\d{3}[-. ]\d{4}\b",
+        );
+        Self::new(RedactionKind::Phone, pattern, |candidate| {
+            (7..=15).contains(&digits(candidate).len())
+        })
+        .expect("valid phone pattern")
+    }
+
+    /// Detects credit card numbers that pass the Luhn check.
+    pub fn credit_card() -> Self {
+        Self::new(RedactionKind::CreditCard, r"\b\d(?:[ -]?\d){12,18}\b", |candidate| {
+            luhn_valid(&digits(candidate))
+        })
+        .expect("valid credit card pattern")
+    }
+
+    /// Detects IBANs that pass the mod-97 check.
+    pub fn iban() -> Self {
+        // Spaces only between groups of four, so following words aren't taken in
+        Self::new(RedactionKind::Iban, r"(?i)\b[a-z]{2}\d{2}(?: ?[a-z0-9]{4}){2,7}(?: ?\d{1,4})?\b", iban_valid)
+            .expect("valid IBAN pattern")
+    }
+}
+
+impl Detector for PatternDetector {
+    fn kind(&self) -> RedactionKind {
+        self.kind
+    }
+
+    fn find(&self, text: &str) -> Vec<Range<usize>> {
+        self.regex
+            .find_iter(text)
+            .filter(|m| (self.validate)(m.as_str()))
+            .map(|m| m.range())
+            .collect()
+    }
+}
+
+/// Detector for user-defined terms, matched as whole words regardless of case.
+pub struct TermDetector {
+    /// Alternation of all terms, None without terms
+    regex: Option<Regex>,
+}
+
+impl TermDetector {
+    /// Creates a detector for the given terms.
+    pub fn new(terms: &[String]) -> Self {
+        let alternatives: Vec<String> = terms
+            .iter()
+            .map(|term| term.trim())
+            .filter(|term| !term.is_empty())
+            .map(regex::escape)
+            .collect();
+        if alternatives.is_empty() {
+            return Self { regex: None };
+        }
+
+        // Longest first, so a term containing another one wins
+        let mut alternatives = alternatives;
+        alternatives.sort_by_key(|term| std::cmp::Reverse(term.len()));
+        let pattern = format!(r"\b(?:{})\b", alternatives.join("|"));
+        let regex = RegexBuilder::new(&pattern).case_insensitive(true).build().ok();
+        if regex.is_none() {
+            println!("Redaction terms could not be compiled and are ignored");
+        }
+        Self { regex }
+    }
+}
+
+impl Detector for TermDetector {
+    fn kind(&self) -> RedactionKind {
+        RedactionKind::Term
+    }
+
+    fn find(&self, text: &str) -> Vec<Range<usize>> {
+        match &self.regex {
+            Some(regex) => regex.find_iter(text).map(|m| m.range()).collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Configuration for redacting personal information from transcripts
+#[derive(Clone)]
+pub struct RedactionConfig {
+    /// Whether transcripts are redacted at all
+    pub enabled: bool,
+    /// Whether to redact email addresses
+    pub emails: bool,
+    /// Whether to redact phone numbers
+    pub phone_numbers: bool,
+    /// Whether to redact credit card numbers
+    pub credit_cards: bool,
+    /// Whether to redact IBANs
+    pub ibans: bool,
+    /// Additional terms to redact, e.g. names
+    pub terms: Vec<String>,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            emails: true,
+            phone_numbers: true,
+            credit_cards: true,
+            ibans: true,
+            terms: Vec::new(),
+        }
+    }
+}
+
+/// Redactor replaces personal information in transcripts with typed placeholders.
+pub struct Redactor {
+    /// Whether transcripts are redacted at all
+    enabled: bool,
+    /// Detectors in order of precedence for matches of equal length
+    detectors: Vec<Box<dyn Detector>>,
+}
+
+impl Redactor {
+    /// Creates a new Redactor with the detectors enabled in the configuration.
+    pub fn with_config(config: &RedactionConfig) -> Self {
+        let mut detectors: Vec<Box<dyn Detector>> = Vec::new();
+        // Card numbers and IBANs before phone numbers, which match their digits too
+        if config.credit_cards {
+            detectors.push(Box::new(PatternDetector::credit_card()));
+        }
+        if config.ibans {
+            detectors.push(Box::new(PatternDetector::iban()));
+        }
+        if config.emails {
+            detectors.push(Box::new(PatternDetector::email()));
+        }
+        if config.phone_numbers {
+            detectors.push(Box::new(PatternDetector::phone()));
+        }
+        if !config.terms.is_empty() {
+            detectors.push(Box::new(TermDetector::new(&config.terms)));
+        }
+
+        Self {
+            enabled: config.enabled,
+            detectors,
+        }
+    }
+
+    /// Adds a detector, with lower precedence than the existing ones.
+    pub fn add_detector(&mut self, detector: Box<dyn Detector>) {
+        self.detectors.push(detector);
+    }
+
+    /// Redacts a segment in place, keeping its tokens and text in sync.
+    ///
+    /// Tokens overlapping a redacted span are replaced by a single token holding
+    /// the placeholder, and the span is recorded in the segment's `redactions`.
+    /// The spoken form of a redacted segment is dropped, since numbers in it are
+    /// spelled out where the detectors can't see them.
+    ///
+    /// # Arguments
+    ///
+    /// * `segment` - The segment to redact
+    pub fn redact_segment(&self, segment: &mut TranscriptSegment) {
+        if !self.enabled {
+            return;
+        }
+        let matches = self.find(&segment.text);
+        if matches.is_empty() {
+            if let Some(spoken) = segment.spoken_text.take() {
+                segment.spoken_text = Some(self.redact(&spoken));
+            }
+            return;
+        }
+        segment.spoken_text = None;
+
+        // Segments without tokens are treated as a single token
+        let has_tokens = !segment.tokens.is_empty()
+            && segment.tokens.iter().map(|t| t.text.as_str()).collect::<String>() == segment.text;
+        let tokens = if has_tokens {
+            std::mem::take(&mut segment.tokens)
+        } else {
+            vec![TranscriptToken {
+                text: segment.text.clone(),
+                probability: 1.0,
+                start: segment.start,
+                end: segment.end,
+            }]
+        };
+
+        let (tokens, redactions) = replace_spans(&segment.text, tokens, &matches);
+        for redaction in &redactions {
+            println!("Redacted {:?} at {:.2}s", redaction.kind, redaction.start_time);
+        }
+
+        if has_tokens {
+            segment.set_tokens(tokens);
+        } else {
+            segment.text = tokens.into_iter().map(|t| t.text).collect();
+        }
+        segment.redactions.extend(redactions);
+    }
+
+    /// Redacts plain text.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text to redact
+    ///
+    /// # Returns
+    ///
+    /// * `String` - The text with placeholders in place of personal information
+    pub fn redact(&self, text: &str) -> String {
+        if !self.enabled {
+            return text.to_string();
+        }
+
+        let mut redacted = String::with_capacity(text.len());
+        let mut cursor = 0;
+        for (kind, range) in self.find(text) {
+            redacted.push_str(&text[cursor..range.start]);
+            redacted.push_str(kind.placeholder());
+            cursor = range.end;
+        }
+        redacted.push_str(&text[cursor..]);
+        redacted
+    }
+
+    /// Runs all detectors and resolves overlapping matches: the earliest match
+    /// wins, then the longest, then the detector added first.
+    fn find(&self, text: &str) -> Vec<(RedactionKind, Range<usize>)> {
+        let mut candidates: Vec<(usize, RedactionKind, Range<usize>)> = self
+            .detectors
+            .iter()
+            .enumerate()
+            .flat_map(|(order, detector)| {
+                let kind = detector.kind();
+                detector.find(text).into_iter().map(move |range| (order, kind, range))
+            })
+            .filter(|(_, _, range)| !range.is_empty())
+            .collect();
+        candidates.sort_by_key(|(order, _, range)| (range.start, std::cmp::Reverse(range.end), *order));
+
+        let mut matches: Vec<(RedactionKind, Range<usize>)> = Vec::new();
+        for (_, kind, range) in candidates {
+            if matches.last().map_or(true, |(_, last)| range.start >= last.end) {
+                matches.push((kind, range));
+            }
+        }
+        matches
+    }
+}
+
+/// Replaces the matched byte ranges of `text`, the concatenation of `tokens`,
+/// with placeholders.
+///
+/// # Returns
+///
+/// * `(Vec<TranscriptToken>, Vec<Redaction>)` - The new tokens and the redacted spans
+fn replace_spans(
+    text: &str,
+    tokens: Vec<TranscriptToken>,
+    matches: &[(RedactionKind, Range<usize>)],
+) -> (Vec<TranscriptToken>, Vec<Redaction>) {
+    let mut starts = Vec::with_capacity(tokens.len());
+    let mut offset = 0;
+    for token in &tokens {
+        starts.push(offset);
+        offset += token.text.len();
+    }
+    let end_of = |index: usize| starts[index] + tokens[index].text.len();
+    // Index of the token containing a byte offset
+    let token_at = |offset: usize| starts.partition_point(|&start| start <= offset).saturating_sub(1);
+
+    let mut replaced = Vec::with_capacity(tokens.len());
+    let mut redactions = Vec::new();
+    let mut length = 0;
+    let mut index = 0;
+    let mut next_match = 0;
+
+    while index < tokens.len() {
+        let Some((_, range)) = matches.get(next_match).filter(|(_, range)| range.start < end_of(index)) else {
+            length += tokens[index].text.len();
+            replaced.push(tokens[index].clone());
+            index += 1;
+            continue;
+        };
+
+        // Group the tokens covered by this match and any match sharing a token with it
+        let first = index;
+        let mut last = token_at(range.end - 1);
+        let mut group_end = next_match + 1;
+        while let Some((_, range)) = matches.get(group_end).filter(|(_, range)| range.start < end_of(last)) {
+            last = last.max(token_at(range.end - 1));
+            group_end += 1;
+        }
+
+        let mut token_text = String::new();
+        let mut cursor = starts[first];
+        for (kind, range) in &matches[next_match..group_end] {
+            token_text.push_str(&text[cursor..range.start]);
+            let start = length + token_text.len();
+            token_text.push_str(kind.placeholder());
+            redactions.push(Redaction {
+                kind: *kind,
+                start,
+                end: length + token_text.len(),
+                start_time: tokens[token_at(range.start)].start,
+                end_time: tokens[token_at(range.end - 1)].end,
+            });
+            cursor = range.end;
+        }
+        token_text.push_str(&text[cursor..end_of(last)]);
+
+        length += token_text.len();
+        replaced.push(TranscriptToken {
+            text: token_text,
+            probability: tokens[first..=last].iter().map(|t| t.probability).fold(1.0, f32::min),
+            start: tokens[first].start,
+            end: tokens[last].end,
+        });
+        index = last + 1;
+        next_match = group_end;
+    }
+
+    (replaced, redactions)
+}
+
+/// Digits of a candidate, without separators.
+fn digits(candidate: &str) -> String {
+    candidate.chars().filter(char::is_ascii_digit).collect()
+}
+
+/// Luhn checksum used by payment card numbers.
+fn luhn_valid(digits: &str) -> bool {
+    if !(13..=19).contains(&digits.len()) {
+        return false;
+    }
+
+    let sum: u32 = digits
+        .chars()
+        .rev()
+        .filter_map(|c| c.to_digit(10))
+        .enumerate()
+        .map(|(i, digit)| match i % 2 {
+            0 => digit,
+            _ if digit * 2 > 9 => digit * 2 - 9,
+            _ => digit * 2,
+        })
+        .sum();
+    sum % 10 == 0
+}
+
+/// ISO 13616 check: the IBAN, rotated by four characters and with letters
+/// written as numbers, is 1 modulo 97.
+fn iban_valid(candidate: &str) -> bool {
+    let iban: String = candidate
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    if !(15..=34).contains(&iban.len()) {
+        return false;
+    }
+
+    let (head, tail) = iban.split_at(4);
+    let mut remainder = 0u32;
+    for c in tail.chars().chain(head.chars()) {
+        let value = match c.to_digit(36) {
+            Some(value) => value,
+            None => return false,
+        };
+        remainder = if value < 10 {
+            (remainder * 10 + value) % 97
+        } else {
+            (remainder * 100 + value) % 97
+        };
+    }
+    remainder == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn found(detector: &PatternDetector, text: &str) -> Vec<String> {
+        detector.find(text).into_iter().map(|range| text[range].to_string()).collect()
+    }
+
+    #[test]
+    fn luhn_accepts_valid_card_numbers_only() {
+        assert!(luhn_valid("4111111111111111"));
+        assert!(luhn_valid("378282246310005"));
+        assert!(!luhn_valid("4111111111111112"));
+        // Too short for a card, even though the checksum holds
+        assert!(!luhn_valid("18"));
+    }
+
+    #[test]
+    fn iban_check_accepts_valid_ibans_only() {
+        assert!(iban_valid("DE89 3704 0044 0532 0130 00"));
+        assert!(iban_valid("gb82west12345698765432"));
+        assert!(!iban_valid("DE89 3704 0044 0532 0130 01"));
+        assert!(!iban_valid("DE89 37O4 0044 0532 0130 00"));
+    }
+
+    #[test]
+    fn phone_numbers_need_a_prefix_or_grouping() {
+        let phone = PatternDetector::phone();
+        assert_eq!(found(&phone, "call +49 30 1234567 now"), ["+49 30 1234567"]);
+        assert_eq!(found(&phone, "call 030 1234567 now"), ["030 1234567"]);
+        assert_eq!(found(&phone, "call (555) 123-4567 now"), ["(555) 123-4567"]);
+        assert_eq!(found(&phone, "call 555-123-4567 now"), ["555-123-4567"]);
+        assert!(found(&phone, "it costs 8000000 dollars").is_empty());
+        assert!(found(&phone, "in 2019 2020 2021").is_empty());
+    }
+
+    #[test]
+    fn redacted_segments_lose_their_spoken_form() {
+        let redactor = Redactor::with_config(&RedactionConfig::default());
+        let mut segment = TranscriptSegment::empty(0, 0.0, 2.0);
+        segment.text = " My card is 4111 1111 1111 1111.".to_string();
+        segment.spoken_text = Some(" my card is four one one one ...".to_string());
+
+        redactor.redact_segment(&mut segment);
+        assert_eq!(segment.text, " My card is [CREDIT_CARD].");
+        assert!(segment.spoken_text.is_none());
+        assert_eq!(segment.redactions.len(), 1);
+        assert_eq!(segment.redactions[0].kind, RedactionKind::CreditCard);
+    }
+}
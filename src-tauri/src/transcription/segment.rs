@@ -0,0 +1,233 @@
+use serde::Serialize;
+
+use crate::transcription::redaction::Redaction;
+use crate::transcription::service::TranscriptionTask;
+
+/// A single decoded token with its probability
+#[derive(Debug, Clone, Serialize)]
+pub struct TranscriptToken {
+    /// Text of the token (may start with a space)
+    pub text: String,
+    /// Probability Whisper assigned to the token (0.0 - 1.0)
+    pub probability: f32,
+    /// Start of the token in seconds
+    pub start: f32,
+    /// End of the token in seconds
+    pub end: f32,
+}
+
+/// A word assembled from one or more sub-word tokens
+#[derive(Debug, Clone, Serialize)]
+pub struct TranscriptWord {
+    /// Text of the word, without surrounding whitespace
+    pub text: String,
+    /// Start of the word in seconds
+    pub start: f32,
+    /// End of the word in seconds
+    pub end: f32,
+    /// Average probability of the word's tokens
+    pub probability: f32,
+}
+
+/// A transcribed segment of audio with timing and confidence information
+#[derive(Debug, Clone, Serialize)]
+pub struct TranscriptSegment {
+    /// Start of the segment in seconds
+    pub start: f32,
+    /// End of the segment in seconds
+    pub end: f32,
+    /// Transcribed text
+    pub text: String,
+    /// Text tokens of the segment, special tokens excluded
+    pub tokens: Vec<TranscriptToken>,
+    /// Words of the segment with their own timing, built from the tokens
+    pub words: Vec<TranscriptWord>,
+    /// Average log-probability of the text tokens
+    pub avg_logprob: f32,
+    /// Probability Whisper assigned to the no-speech token, None if the engine
+    /// doesn't report it
+    pub no_speech_prob: Option<f32>,
+    /// Language the segment was transcribed in
+    pub language: String,
+    /// Probability of the detected language, None if the language was configured
+    pub language_probability: Option<f32>,
+    /// Whether the segment was transcribed or translated to English
+    pub task: TranscriptionTask,
+    /// Id of the window the segment belongs to; interim and final results of a window share it
+    pub id: u64,
+    /// Whether this is the final result for the window, or an interim hypothesis
+    /// that will be replaced
+    pub is_final: bool,
+    /// Speaker label, numbered from 0 in order of appearance (None without diarization)
+    pub speaker: Option<u32>,
+    /// Text as spoken, before normalization (None unless the spoken form is kept,
+    /// and for redacted segments)
+    pub spoken_text: Option<String>,
+    /// Spans of the text replaced by placeholders to hide personal information
+    pub redactions: Vec<Redaction>,
+}
+
+impl TranscriptSegment {
+    /// A final result without text for a window, which replaces any interim text
+    /// shown for it.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Id of the window
+    /// * `start` - Start of the window in seconds
+    /// * `end` - End of the window in seconds
+    pub fn empty(id: u64, start: f32, end: f32) -> Self {
+        Self {
+            start,
+            end,
+            text: String::new(),
+            tokens: Vec::new(),
+            words: Vec::new(),
+            avg_logprob: 0.0,
+            no_speech_prob: None,
+            language: String::new(),
+            language_probability: None,
+            task: TranscriptionTask::Transcribe,
+            id,
+            is_final: true,
+            speaker: None,
+            spoken_text: None,
+            redactions: Vec::new(),
+        }
+    }
+
+    /// Moves the segment by the given offset, e.g. from window time to session time.
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds` - Offset to add to the start and end times
+    pub fn shift(&mut self, seconds: f32) {
+        self.start += seconds;
+        self.end += seconds;
+        for token in self.tokens.iter_mut() {
+            token.start += seconds;
+            token.end += seconds;
+        }
+        for word in self.words.iter_mut() {
+            word.start += seconds;
+            word.end += seconds;
+        }
+        for redaction in self.redactions.iter_mut() {
+            redaction.start_time += seconds;
+            redaction.end_time += seconds;
+        }
+    }
+
+    /// Rebuilds the words from the tokens, merging sub-word tokens.
+    ///
+    /// A token starting with whitespace starts a new word; punctuation-only
+    /// tokens are attached to the word before them.
+    pub fn update_words(&mut self) {
+        let mut words: Vec<(TranscriptWord, usize)> = Vec::new();
+
+        for token in &self.tokens {
+            let starts_word = token.text.starts_with(char::is_whitespace)
+                && token.text.chars().any(char::is_alphanumeric);
+            match words.last_mut() {
+                Some((word, count)) if !starts_word => {
+                    word.text.push_str(token.text.trim_start());
+                    word.end = token.end;
+                    word.probability += token.probability;
+                    *count += 1;
+                }
+                _ => words.push((
+                    TranscriptWord {
+                        text: token.text.clone(),
+                        start: token.start,
+                        end: token.end,
+                        probability: token.probability,
+                    },
+                    1,
+                )),
+            }
+        }
+
+        self.words = words
+            .into_iter()
+            .map(|(mut word, count)| {
+                word.text = word.text.trim().to_string();
+                word.probability /= count as f32;
+                word
+            })
+            .filter(|word| !word.text.is_empty())
+            .collect();
+    }
+
+    /// Combines the segments of a window into one, e.g. for an interim hypothesis.
+    ///
+    /// # Arguments
+    ///
+    /// * `segments` - Consecutive segments of one window
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Self>` - The combined segment, None if there are no segments
+    pub fn merge(segments: Vec<Self>) -> Option<Self> {
+        let mut segments = segments.into_iter();
+        let mut merged = segments.next()?;
+        let mut logprob_sum = merged.avg_logprob * merged.tokens.len() as f32;
+        let mut no_speech_probs: Vec<f32> = merged.no_speech_prob.into_iter().collect();
+
+        for segment in segments {
+            logprob_sum += segment.avg_logprob * segment.tokens.len() as f32;
+            no_speech_probs.extend(segment.no_speech_prob);
+            merged.end = segment.end;
+            let offset = merged.text.len();
+            merged.redactions.extend(segment.redactions.into_iter().map(|mut redaction| {
+                redaction.start += offset;
+                redaction.end += offset;
+                redaction
+            }));
+            merged.text.push_str(&segment.text);
+            // The spoken form is only complete if every segment kept it
+            match (merged.spoken_text.as_mut(), segment.spoken_text) {
+                (Some(spoken), Some(more)) => spoken.push_str(&more),
+                _ => merged.spoken_text = None,
+            }
+            merged.tokens.extend(segment.tokens);
+            merged.words.extend(segment.words);
+        }
+
+        if !merged.tokens.is_empty() {
+            merged.avg_logprob = logprob_sum / merged.tokens.len() as f32;
+        }
+        if !no_speech_probs.is_empty() {
+            merged.no_speech_prob = Some(no_speech_probs.iter().sum::<f32>() / no_speech_probs.len() as f32);
+        }
+        Some(merged)
+    }
+
+    /// Removes the tokens from the segment, grouped into words.
+    ///
+    /// A token starting with whitespace starts a new word, so each word carries
+    /// its leading whitespace and trailing punctuation.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Vec<TranscriptToken>>` - The tokens of each word
+    pub fn take_words(&mut self) -> Vec<Vec<TranscriptToken>> {
+        let mut words: Vec<Vec<TranscriptToken>> = Vec::new();
+        for token in self.tokens.drain(..) {
+            match words.last_mut() {
+                Some(word) if !token.text.starts_with(char::is_whitespace) => word.push(token),
+                _ => words.push(vec![token]),
+            }
+        }
+        words
+    }
+
+    /// Replaces the tokens and rebuilds the text from them.
+    ///
+    /// # Arguments
+    ///
+    /// * `tokens` - The new tokens
+    pub fn set_tokens(&mut self, tokens: Vec<TranscriptToken>) {
+        self.text = tokens.iter().map(|t| t.text.as_str()).collect();
+        self.tokens = tokens;
+    }
+}
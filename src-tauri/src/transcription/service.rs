@@ -1,9 +1,53 @@
-use crate::transcription::whisper;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::transcription::segment::TranscriptSegment;
+use crate::transcription::vocabulary::{Vocabulary, VocabularyConfig};
+use crate::transcription::engine::{EngineKind, TranscribeOptions, TranscriptionEngine};
+use crate::transcription::filter::{FilterConfig, HallucinationFilter};
+use crate::transcription::loader::ModelState;
+use crate::transcription::mock::{MockEngine, MockEngineConfig};
+use crate::transcription::normalize::{NormalizationConfig, Normalizer};
+use crate::transcription::redaction::{RedactionConfig, Redactor};
+use crate::transcription::whisper::{self, DecodingConfig, WhisperEngine};
+
+/// What Whisper should produce from the audio
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TranscriptionTask {
+    /// Transcribe speech in its spoken language
+    #[default]
+    Transcribe,
+    /// Translate speech into English
+    Translate,
+}
+
+/// Configuration for carrying text over between consecutive windows
+#[derive(Clone)]
+pub struct ContextConfig {
+    /// Whether previously transcribed text is used as the prompt for the next window
+    pub enabled: bool,
+    /// Maximum number of characters of previous text passed as the prompt
+    pub max_chars: usize,
+    /// Seconds without transcribed speech after which the context is dropped
+    pub reset_after_silence_seconds: f32,
+}
+
+impl Default for ContextConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_chars: 200,
+            reset_after_silence_seconds: 10.0,
+        }
+    }
+}
 
 /// Configuration for the transcription service
 #[derive(Clone)]
 pub struct TranscriptionConfig {
-    /// Language to use for transcription
+    /// Language to use for transcription ("auto" to detect it per window)
     pub language: String,
     /// Minimum duration in seconds required for transcription
     pub min_duration_seconds: f32,
@@ -11,6 +55,26 @@ pub struct TranscriptionConfig {
     pub sample_rate: usize,
     /// Path to the Whisper model file
     pub model_path: String,
+    /// Whether to transcribe or translate to English
+    pub task: TranscriptionTask,
+    /// Context carry-over between consecutive windows
+    pub context: ContextConfig,
+    /// Custom vocabulary used for prompting and correction
+    pub vocabulary: VocabularyConfig,
+    /// Whisper decoding parameters
+    pub decoding: DecodingConfig,
+    /// Engine used for transcription
+    pub engine: EngineKind,
+    /// Script for the mock engine
+    pub mock: MockEngineConfig,
+    /// Whether to run a short inference on silence after loading, to avoid first-call latency
+    pub warm_up: bool,
+    /// Filtering of hallucinations and artifacts in the engine output
+    pub filter: FilterConfig,
+    /// Rewriting of spoken numbers, dates and the like into written form
+    pub normalization: NormalizationConfig,
+    /// Masking of personal information before text leaves the service
+    pub redaction: RedactionConfig,
 }
 
 impl Default for TranscriptionConfig {
@@ -20,14 +84,82 @@ impl Default for TranscriptionConfig {
             min_duration_seconds: 1.0,
             sample_rate: 16000,
             model_path: "model/ggml-tiny.en.bin".to_string(),
+            task: TranscriptionTask::Transcribe,
+            context: ContextConfig::default(),
+            vocabulary: VocabularyConfig::default(),
+            decoding: DecodingConfig::default(),
+            engine: EngineKind::Whisper,
+            mock: MockEngineConfig::default(),
+            warm_up: true,
+            filter: FilterConfig::default(),
+            normalization: NormalizationConfig::default(),
+            redaction: RedactionConfig::default(),
         }
     }
 }
 
-/// TranscriptionService provides an interface to the Whisper transcription.
+/// An engine shared between the service and the inference running outside of it
+type SharedEngine = Arc<Mutex<Box<dyn TranscriptionEngine>>>;
+
+/// Everything needed to run the engine on one window, captured from the service so
+/// inference doesn't hold the service
+struct WindowRun {
+    /// Engine at the time the window started; a model switch applies from the next window
+    engine: SharedEngine,
+    /// Language code, or "auto"
+    language: String,
+    /// Whether to transcribe or translate
+    task: TranscriptionTask,
+    /// Vocabulary and context prompt
+    prompt: Option<String>,
+    /// Decoding parameters
+    decoding: DecodingConfig,
+    /// Context generation the prompt was taken from
+    generation: u64,
+    /// Whether the window is final and its text becomes context
+    is_final: bool,
+    /// Duration of the window in seconds
+    duration: f32,
+}
+
+impl WindowRun {
+    /// Runs the engine on the window; only the engine is locked meanwhile.
+    fn infer(&self, samples: &[f32]) -> Result<Vec<TranscriptSegment>, String> {
+        let options = TranscribeOptions {
+            language: &self.language,
+            task: self.task,
+            initial_prompt: self.prompt.as_deref(),
+            decoding: &self.decoding,
+        };
+        self.engine.lock().unwrap().transcribe(samples, &options)
+    }
+}
+
+/// TranscriptionService provides an interface to the transcription engine.
 pub struct TranscriptionService {
     /// Configuration for the transcription service
     config: TranscriptionConfig,
+    /// Transcription engine, present once the service is initialized
+    engine: Option<SharedEngine>,
+    /// Recently transcribed text, used as the prompt for the next window
+    context: String,
+    /// Seconds of audio since speech was last transcribed
+    silence_seconds: f32,
+    /// Custom vocabulary, loaded when the service is initialized
+    vocabulary: Option<Vocabulary>,
+    /// Loading status of the engine's model
+    model_state: ModelState,
+    /// Filter removing hallucinated text from the engine output
+    filter: HallucinationFilter,
+    /// Normalizer applied to the filtered and corrected text
+    normalizer: Normalizer,
+    /// Redactor masking personal information in the normalized text
+    redactor: Redactor,
+    /// Whether the last final window ended a sentence, so the next one starts with a capital
+    sentence_ended: bool,
+    /// Incremented whenever the context is reset, so windows started before the
+    /// reset don't add to the new context
+    generation: u64,
 }
 
 impl TranscriptionService {
@@ -39,25 +171,128 @@ impl TranscriptionService {
     /// Creates a new TranscriptionService with the specified configuration.
     pub fn with_config(config: TranscriptionConfig) -> Self {
         Self {
+            filter: HallucinationFilter::with_config(config.filter.clone()),
+            normalizer: Normalizer::with_config(config.normalization.clone()),
+            redactor: Redactor::with_config(&config.redaction),
             config,
+            engine: None,
+            context: String::new(),
+            silence_seconds: 0.0,
+            vocabulary: None,
+            model_state: ModelState::default(),
+            sentence_ended: true,
+            generation: 0,
         }
     }
 
-    /// Initializes the transcription service and underlying model.
+    /// Initializes the transcription service by creating the configured engine.
     ///
-    /// # Arguments
+    /// This blocks while the model loads; use `loader::load_model` to load it in
+    /// the background instead.
     ///
-    /// * `model_path` - Path to the Whisper model file
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - Ok if successful, Err with error message otherwise
+    pub fn initialize(&mut self) -> Result<(), String> {
+        self.prepare()?;
+        let engine = Self::create_engine(&self.config)?;
+        let config = self.config.clone();
+        self.set_engine(engine, &config);
+        Ok(())
+    }
+
+    /// Validates the configuration and loads the vocabulary, without creating the engine.
     ///
     /// # Returns
     ///
     /// * `Result<(), String>` - Ok if successful, Err with error message otherwise
-    pub fn initialize(&self) -> Result<(), String> {
-        // Initialize the underlying whisper model
-        whisper::init(&self.config.model_path)
+    pub fn prepare(&mut self) -> Result<(), String> {
+        if !whisper::is_supported_language(&self.config.language) {
+            return Err(format!("Unsupported transcription language \"{}\"", self.config.language));
+        }
+        self.config.decoding.validate()?;
+
+        self.vocabulary = Some(Vocabulary::from_config(&self.config.vocabulary)?);
+        Ok(())
+    }
+
+    /// Creates the engine described by a configuration, loading the Whisper model if needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Configuration naming the engine and model
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Box<dyn TranscriptionEngine>, String>` - The engine, or an error message
+    pub fn create_engine(config: &TranscriptionConfig) -> Result<Box<dyn TranscriptionEngine>, String> {
+        let engine: Box<dyn TranscriptionEngine> = match config.engine {
+            EngineKind::Whisper => Box::new(WhisperEngine::load(&config.model_path)?),
+            EngineKind::Mock => Box::new(MockEngine::with_config(config.mock.clone())),
+        };
+        Ok(engine)
     }
 
-    /// Transcribes the provided audio samples using Whisper.
+    /// Runs one inference on a second of silence so the first real window doesn't
+    /// pay for lazy allocations inside the engine.
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The engine to warm up
+    /// * `config` - Configuration providing the language and decoding parameters
+    pub fn warm_up(engine: &mut dyn TranscriptionEngine, config: &TranscriptionConfig) {
+        let silence = vec![0.0; config.sample_rate];
+        let options = TranscribeOptions {
+            language: &config.language,
+            task: config.task,
+            initial_prompt: None,
+            decoding: &config.decoding,
+        };
+        match engine.transcribe(&silence, &options) {
+            Ok(_) => println!("Transcription engine \"{}\" warmed up", engine.name()),
+            Err(err) => println!("Warm-up of transcription engine failed: {}", err),
+        }
+    }
+
+    /// Returns the configuration of the service.
+    pub fn config(&self) -> &TranscriptionConfig {
+        &self.config
+    }
+
+    /// Returns the path of the Whisper model the service was configured with or last switched to.
+    pub fn model_path(&self) -> &str {
+        &self.config.model_path
+    }
+
+    /// Swaps in a newly created engine; applies from the next window on.
+    ///
+    /// The engine is created by the caller so the service stays available for
+    /// transcription while the model loads.
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The new engine
+    /// * `config` - Configuration the engine was created from
+    pub fn set_engine(&mut self, engine: Box<dyn TranscriptionEngine>, config: &TranscriptionConfig) {
+        println!("Transcription engine \"{}\" ready", engine.name());
+        self.engine = Some(Arc::new(Mutex::new(engine)));
+        self.config.engine = config.engine;
+        self.config.model_path = config.model_path.clone();
+        // Context decoded by the previous model may not suit the new one
+        self.reset_context();
+    }
+
+    /// Returns the loading status of the model.
+    pub fn model_state(&self) -> &ModelState {
+        &self.model_state
+    }
+
+    /// Records the loading status of the model.
+    pub fn set_model_state(&mut self, state: ModelState) {
+        self.model_state = state;
+    }
+
+    /// Transcribes the provided audio samples using the configured engine.
     ///
     /// # Arguments
     ///
@@ -65,16 +300,274 @@ impl TranscriptionService {
     ///
     /// # Returns
     ///
-    /// * `Option<String>` - Transcribed text if successful, None otherwise
-    pub fn transcribe(&self, samples: &[f32]) -> Option<String> {
+    /// * `Option<Vec<TranscriptSegment>>` - Transcribed segments, with times relative to
+    ///   the start of `samples`, if successful, None otherwise
+    pub fn transcribe(&mut self, samples: &[f32]) -> Option<Vec<TranscriptSegment>> {
+        let run = self.begin_window(samples.len(), true)?;
+        let segments = run.infer(samples);
+        self.finish_window(run, samples, segments)
+    }
+
+    /// Transcribes a window that is still growing, for an interim result.
+    ///
+    /// Unlike `transcribe`, the text is not carried over as context, since the
+    /// final transcription of the same window follows.
+    ///
+    /// # Arguments
+    ///
+    /// * `samples` - Audio samples received so far for the window
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Vec<TranscriptSegment>>` - Transcribed segments, with times relative to
+    ///   the start of `samples`, if successful, None otherwise
+    pub fn transcribe_interim(&mut self, samples: &[f32]) -> Option<Vec<TranscriptSegment>> {
+        let run = self.begin_window(samples.len(), false)?;
+        let segments = run.infer(samples);
+        self.finish_window(run, samples, segments)
+    }
+
+    /// Transcribes a window of a shared service, holding the service only to read its
+    /// settings before and to post-process the text after inference.
+    ///
+    /// Commands such as switching the task or the model stay responsive while the
+    /// engine runs. Concurrent windows still run one at a time on the engine.
+    ///
+    /// # Arguments
+    ///
+    /// * `service` - The shared service
+    /// * `samples` - Audio samples to transcribe
+    /// * `is_final` - Whether the window is complete (see `transcribe`) or still
+    ///   growing (see `transcribe_interim`)
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Vec<TranscriptSegment>>` - Transcribed segments, with times relative to
+    ///   the start of `samples`, if successful, None otherwise
+    pub fn transcribe_shared(service: &Mutex<Self>, samples: &[f32], is_final: bool) -> Option<Vec<TranscriptSegment>> {
+        let run = service.lock().unwrap().begin_window(samples.len(), is_final)?;
+        let segments = run.infer(samples);
+        service.lock().unwrap().finish_window(run, samples, segments)
+    }
+
+    /// Captures what the engine needs for a window, or returns None if the window
+    /// is too short or no engine is loaded.
+    fn begin_window(&self, sample_count: usize, is_final: bool) -> Option<WindowRun> {
         // Check if we have enough audio
         let min_samples = (self.config.min_duration_seconds * self.config.sample_rate as f32) as usize;
-        if samples.len() < min_samples {
+        if sample_count < min_samples {
             println!("Less than {}s of audio. Skipping...", self.config.min_duration_seconds);
             return None;
         }
 
-        // Use the whisper module to transcribe
-        whisper::transcribe(samples)
+        let engine = match &self.engine {
+            Some(engine) => engine.clone(),
+            None => {
+                println!("Transcription model not loaded. Skipping...");
+                return None;
+            }
+        };
+
+        // Condition the decoder on the vocabulary and on what was said just before this window
+        let mut prompt = self.vocabulary.as_ref().and_then(|v| v.prompt()).unwrap_or_default();
+        if self.config.context.enabled && !self.context.is_empty() {
+            if !prompt.is_empty() {
+                prompt.push(' ');
+            }
+            prompt.push_str(&self.context);
+        }
+
+        Some(WindowRun {
+            engine,
+            language: self.config.language.clone(),
+            task: self.config.task,
+            prompt: (!prompt.is_empty()).then_some(prompt),
+            decoding: self.config.decoding.clone(),
+            generation: self.generation,
+            is_final,
+            duration: sample_count as f32 / self.config.sample_rate as f32,
+        })
+    }
+
+    /// Runs the filter, vocabulary correction, normalization and redaction on the
+    /// engine output of a window.
+    fn finish_window(
+        &mut self,
+        run: WindowRun,
+        samples: &[f32],
+        segments: Result<Vec<TranscriptSegment>, String>,
+    ) -> Option<Vec<TranscriptSegment>> {
+        let segments = match segments {
+            Ok(segments) => segments,
+            Err(err) => {
+                println!("Transcription failed: {}", err);
+                return None;
+            }
+        };
+
+        // Drop text produced from silence or noise, so it doesn't reach the context either
+        let mut segments = self.filter.apply(segments, samples, self.config.sample_rate);
+
+        // Fix near-misses of vocabulary terms
+        if let Some(vocabulary) = &self.vocabulary {
+            for segment in segments.iter_mut() {
+                vocabulary.correct_segment(segment);
+            }
+        }
+
+        // Written forms only after correction, which matches terms as spoken
+        let mut starts_sentence = self.sentence_ended;
+        for segment in segments.iter_mut() {
+            starts_sentence = self.normalizer.normalize_segment(segment, starts_sentence);
+        }
+
+        // Redacted on written forms, where numbers are digits; nothing unredacted
+        // leaves the service, not even as context
+        for segment in segments.iter_mut() {
+            self.redactor.redact_segment(segment);
+        }
+
+        // Words are built last, so they reflect the corrected tokens
+        for segment in segments.iter_mut() {
+            segment.update_words();
+        }
+
+        // A window started before the context was reset belongs to the previous session
+        if run.is_final && run.generation == self.generation {
+            self.update_context(&segments, run.duration);
+            self.sentence_ended = starts_sentence;
+        }
+        Some(segments)
+    }
+
+    /// Drops the carried-over context, e.g. when a new session starts.
+    pub fn reset_context(&mut self) {
+        self.generation += 1;
+        self.context.clear();
+        self.silence_seconds = 0.0;
+        self.sentence_ended = true;
+    }
+
+    /// Appends the text of a transcribed window to the context, or drops the context
+    /// after a long enough stretch without speech.
+    fn update_context(&mut self, segments: &[TranscriptSegment], duration: f32) {
+        if !self.config.context.enabled {
+            return;
+        }
+
+        let text: String = segments.iter().map(|segment| segment.text.as_str()).collect();
+        if text.trim().is_empty() {
+            self.silence_seconds += duration;
+            if self.silence_seconds >= self.config.context.reset_after_silence_seconds {
+                self.context.clear();
+            }
+            return;
+        }
+
+        self.silence_seconds = 0.0;
+        self.context.push_str(&text);
+        self.context = tail_at_word_boundary(&self.context, self.config.context.max_chars);
+    }
+
+    /// Returns the task currently used for transcription.
+    pub fn task(&self) -> TranscriptionTask {
+        self.config.task
+    }
+
+    /// Switches between transcription and translation; applies from the next window on.
+    ///
+    /// # Arguments
+    ///
+    /// * `task` - The task to use
+    pub fn set_task(&mut self, task: TranscriptionTask) {
+        println!("Transcription task set to {:?}", task);
+        self.config.task = task;
+    }
+
+    /// Returns the terms of the custom vocabulary.
+    pub fn vocabulary(&self) -> Vec<String> {
+        self.vocabulary.as_ref().map(|v| v.terms().to_vec()).unwrap_or_default()
+    }
+
+    /// Replaces the terms of the custom vocabulary; applies from the next window on.
+    ///
+    /// # Arguments
+    ///
+    /// * `terms` - The new terms
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - Ok if successful, Err with error message otherwise
+    pub fn set_vocabulary(&mut self, terms: Vec<String>) -> Result<(), String> {
+        match self.vocabulary.as_mut() {
+            Some(vocabulary) => vocabulary.set_terms(terms),
+            None => Err("Transcription service not initialized".to_string()),
+        }
+    }
+}
+
+/// Returns at most the last `max_chars` characters of `text`, starting at a word boundary.
+fn tail_at_word_boundary(text: &str, max_chars: usize) -> String {
+    let char_count = text.chars().count();
+    if char_count <= max_chars {
+        return text.to_string();
     }
-} 
\ No newline at end of file
+
+    let tail: String = text.chars().skip(char_count - max_chars).collect();
+    match tail.find(char::is_whitespace) {
+        Some(index) => tail[index..].trim_start().to_string(),
+        None => tail,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    fn mock_service(responses: &[&str], latency_ms: u64) -> TranscriptionService {
+        let mut service = TranscriptionService::with_config(TranscriptionConfig {
+            engine: EngineKind::Mock,
+            mock: MockEngineConfig {
+                responses_by_input: Default::default(),
+                responses: responses.iter().map(|r| r.to_string()).collect(),
+                latency_ms,
+                fail_every: None,
+            },
+            ..TranscriptionConfig::default()
+        });
+        service.initialize().unwrap();
+        service
+    }
+
+    #[test]
+    fn shared_service_stays_available_during_inference() {
+        let service = Arc::new(Mutex::new(mock_service(&["hello there"], 500)));
+
+        let inference = {
+            let service = service.clone();
+            thread::spawn(move || TranscriptionService::transcribe_shared(&service, &[0.0; 16000], true))
+        };
+        thread::sleep(Duration::from_millis(100));
+
+        let started = Instant::now();
+        service.lock().unwrap().set_task(TranscriptionTask::Translate);
+        assert!(started.elapsed() < Duration::from_millis(200));
+
+        let segments = inference.join().unwrap().unwrap();
+        assert!(segments[0].text.trim().eq_ignore_ascii_case("hello there"));
+    }
+
+    #[test]
+    fn windows_started_before_a_reset_do_not_become_context() {
+        let service = Mutex::new(mock_service(&["old session"], 0));
+        let run = service.lock().unwrap().begin_window(16000, true).unwrap();
+        let samples = [0.0; 16000];
+        let segments = run.infer(&samples);
+        service.lock().unwrap().reset_context();
+        service.lock().unwrap().finish_window(run, &samples, segments).unwrap();
+
+        assert!(service.lock().unwrap().context.is_empty());
+    }
+}
@@ -0,0 +1,422 @@
+use std::fs;
+use std::path::Path;
+
+use crate::transcription::segment::{TranscriptSegment, TranscriptToken};
+
+/// A term tolerates one edit per this many characters, so terms shorter than this
+/// are only corrected on an exact, case-insensitive match ("Slack" never takes "stack")
+const CHARS_PER_EDIT: usize = 7;
+/// Everyday words that are never replaced, even when close to a term ("motion" for "Notion")
+const COMMON_WORDS: [&str; 96] = [
+    "action", "actions", "almost", "always", "another", "answer", "around", "before", "behind",
+    "better", "between", "bottom", "button", "called", "center", "change", "choice", "client",
+    "closed", "coming", "common", "course", "create", "custom", "during", "editor", "either",
+    "enough", "engine", "except", "export", "figure", "finger", "follow", "friend", "future",
+    "getting", "ground", "happen", "having", "import", "inside", "island", "itself", "letter",
+    "little", "looking", "making", "market", "master", "matter", "member", "method", "middle",
+    "minute", "moment", "motion", "mother", "nation", "nature", "nothing", "notion", "number",
+    "object", "office", "option", "others", "people", "person", "places", "planet", "please",
+    "pretty", "public", "really", "reason", "record", "report", "result", "return", "saying",
+    "second", "should", "simple", "something", "source", "spring", "street", "string", "system",
+    "taking", "things", "though", "through", "travel", "window",
+];
+
+/// Configuration for the custom vocabulary
+#[derive(Clone)]
+pub struct VocabularyConfig {
+    /// Terms listed directly in the configuration
+    pub terms: Vec<String>,
+    /// Optional file with one term per line (`#` starts a comment)
+    pub file: Option<String>,
+    /// Minimum similarity (0.0 - 1.0) for a transcribed phrase to be replaced by a term
+    pub min_similarity: f32,
+}
+
+impl Default for VocabularyConfig {
+    fn default() -> Self {
+        Self {
+            terms: Vec::new(),
+            file: None,
+            min_similarity: 0.8,
+        }
+    }
+}
+
+/// A replacement of one or more consecutive words by a vocabulary term
+struct Correction {
+    /// Index of the first replaced word
+    start: usize,
+    /// Number of replaced words
+    len: usize,
+    /// Text replacing the words, including surrounding whitespace and punctuation
+    replacement: String,
+}
+
+/// Vocabulary biases Whisper towards product names and jargon and corrects
+/// near-misses in its output.
+pub struct Vocabulary {
+    /// Known terms, longest phrases first
+    terms: Vec<String>,
+    /// File the terms are persisted to, if any
+    file: Option<String>,
+    /// Minimum similarity for a fuzzy correction
+    min_similarity: f32,
+}
+
+impl Vocabulary {
+    /// Creates a vocabulary from the configured terms and vocabulary file.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Vocabulary configuration
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, String>` - The vocabulary, or an error if the file can't be read
+    pub fn from_config(config: &VocabularyConfig) -> Result<Self, String> {
+        let mut terms = config.terms.clone();
+
+        if let Some(file) = &config.file {
+            if Path::new(file).exists() {
+                let contents = fs::read_to_string(file)
+                    .map_err(|e| format!("Failed to read vocabulary file {}: {}", file, e))?;
+                terms.extend(
+                    contents
+                        .lines()
+                        .map(str::trim)
+                        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                        .map(str::to_string),
+                );
+            }
+        }
+
+        let mut vocabulary = Self {
+            terms: Vec::new(),
+            file: config.file.clone(),
+            min_similarity: config.min_similarity,
+        };
+        vocabulary.set_terms_in_memory(terms);
+        Ok(vocabulary)
+    }
+
+    /// Returns the current terms.
+    pub fn terms(&self) -> &[String] {
+        &self.terms
+    }
+
+    /// Replaces the terms, persisting them to the vocabulary file if one is configured.
+    ///
+    /// # Arguments
+    ///
+    /// * `terms` - The new terms
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - Ok if successful, Err if the file can't be written
+    pub fn set_terms(&mut self, terms: Vec<String>) -> Result<(), String> {
+        self.set_terms_in_memory(terms);
+
+        if let Some(file) = &self.file {
+            let mut contents = self.terms.join("\n");
+            contents.push('\n');
+            fs::write(file, contents)
+                .map_err(|e| format!("Failed to write vocabulary file {}: {}", file, e))?;
+        }
+        Ok(())
+    }
+
+    /// Builds the prompt text that biases Whisper towards the vocabulary.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<String>` - The prompt, None if the vocabulary is empty
+    pub fn prompt(&self) -> Option<String> {
+        if self.terms.is_empty() {
+            return None;
+        }
+        Some(format!("Glossary: {}.", self.terms.join(", ")))
+    }
+
+    /// Replaces near-misses of vocabulary terms in a transcribed segment.
+    ///
+    /// Corrections are applied to the segment's tokens, so text and tokens stay in
+    /// sync; a corrected span keeps the lowest probability of the tokens it replaces.
+    ///
+    /// # Arguments
+    ///
+    /// * `segment` - The segment to correct in place
+    pub fn correct_segment(&self, segment: &mut TranscriptSegment) {
+        if self.terms.is_empty() {
+            return;
+        }
+
+        if segment.tokens.is_empty() {
+            segment.text = self.correct(&segment.text);
+            return;
+        }
+
+        let word_tokens = segment.take_words();
+
+        let words: Vec<String> = word_tokens
+            .iter()
+            .map(|tokens| tokens.iter().map(|t| t.text.as_str()).collect())
+            .collect();
+        let corrections = self.find_corrections(&words);
+
+        let mut tokens = Vec::new();
+        let mut index = 0;
+        let mut corrections = corrections.into_iter().peekable();
+        while index < word_tokens.len() {
+            match corrections.next_if(|c| c.start == index) {
+                Some(correction) => {
+                    let span = &word_tokens[index..index + correction.len];
+                    let probability = span
+                        .iter()
+                        .flatten()
+                        .map(|t| t.probability)
+                        .fold(1.0, f32::min);
+                    tokens.push(TranscriptToken {
+                        text: correction.replacement,
+                        probability,
+                        start: span[0][0].start,
+                        end: span[correction.len - 1].last().map_or(span[0][0].end, |t| t.end),
+                    });
+                    index += correction.len;
+                }
+                None => {
+                    tokens.extend(word_tokens[index].iter().cloned());
+                    index += 1;
+                }
+            }
+        }
+
+        segment.set_tokens(tokens);
+    }
+
+    /// Replaces near-misses of vocabulary terms in plain text.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text to correct
+    ///
+    /// # Returns
+    ///
+    /// * `String` - The corrected text
+    pub fn correct(&self, text: &str) -> String {
+        let words = split_words(text);
+        let corrections = self.find_corrections(&words);
+
+        let mut output = String::with_capacity(text.len());
+        let mut index = 0;
+        let mut corrections = corrections.into_iter().peekable();
+        while index < words.len() {
+            match corrections.next_if(|c| c.start == index) {
+                Some(correction) => {
+                    output.push_str(&correction.replacement);
+                    index += correction.len;
+                }
+                None => {
+                    output.push_str(&words[index]);
+                    index += 1;
+                }
+            }
+        }
+        output
+    }
+
+    /// Sets the terms without persisting them, dropping blanks and duplicates and
+    /// ordering longer phrases first so they win over their parts.
+    fn set_terms_in_memory(&mut self, terms: Vec<String>) {
+        let mut unique: Vec<String> = Vec::new();
+        for term in terms {
+            let term = term.trim().to_string();
+            if !term.is_empty() && !unique.iter().any(|t| t.eq_ignore_ascii_case(&term)) {
+                unique.push(term);
+            }
+        }
+        unique.sort_by_key(|term| std::cmp::Reverse(term.split_whitespace().count()));
+        self.terms = unique;
+    }
+
+    /// Finds the words to replace by vocabulary terms.
+    ///
+    /// Each word carries its leading whitespace and any punctuation, which is kept
+    /// around the replacement.
+    fn find_corrections(&self, words: &[String]) -> Vec<Correction> {
+        let mut corrections = Vec::new();
+        let mut index = 0;
+
+        while index < words.len() {
+            let mut best: Option<(f32, usize, &String)> = None;
+
+            for term in &self.terms {
+                let len = term.split_whitespace().count();
+                if len == 0 || index + len > words.len() {
+                    continue;
+                }
+
+                let phrase = words[index..index + len]
+                    .iter()
+                    .map(|word| word_core(word).to_lowercase())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let target = term.to_lowercase();
+
+                // An exact match always wins, also over a near-miss of a longer term
+                let similarity = if phrase == target {
+                    1.0
+                } else if is_near_miss(&phrase, &target) {
+                    similarity(&phrase, &target)
+                } else {
+                    0.0
+                };
+
+                if similarity < self.min_similarity {
+                    continue;
+                }
+                match best {
+                    Some((best_similarity, _, _)) if best_similarity >= similarity => {}
+                    _ => best = Some((similarity, len, term)),
+                }
+            }
+
+            match best {
+                Some((_, len, term)) => {
+                    let first = &words[index];
+                    let last = &words[index + len - 1];
+                    let core_start = first.find(word_core(first)).unwrap_or(0);
+                    let last_core = word_core(last);
+                    let core_end = last.rfind(last_core).map_or(last.len(), |i| i + last_core.len());
+                    let replacement = format!("{}{}{}", &first[..core_start], term, &last[core_end..]);
+
+                    if words[index..index + len].concat() != replacement {
+                        corrections.push(Correction { start: index, len, replacement });
+                    }
+                    index += len;
+                }
+                None => index += 1,
+            }
+        }
+
+        corrections
+    }
+}
+
+/// Checks whether a transcribed phrase may be a misspelling of a term: within the
+/// edits the term's length allows, and not an everyday word.
+fn is_near_miss(phrase: &str, term: &str) -> bool {
+    let max_edits = term.chars().count() / CHARS_PER_EDIT;
+    max_edits > 0 && !COMMON_WORDS.contains(&phrase) && levenshtein(phrase, term) <= max_edits
+}
+
+/// Splits text into words, each carrying the whitespace before it.
+fn split_words(text: &str) -> Vec<String> {
+    let mut words: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+
+    for c in text.chars() {
+        if c.is_whitespace() && in_word {
+            words.push(std::mem::take(&mut current));
+            in_word = false;
+        } else if !c.is_whitespace() {
+            in_word = true;
+        }
+        current.push(c);
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+/// Strips whitespace and surrounding punctuation from a word.
+fn word_core(word: &str) -> &str {
+    word.trim().trim_matches(|c: char| !c.is_alphanumeric())
+}
+
+/// Similarity of two strings based on their Levenshtein distance (1.0 = identical).
+pub fn similarity(a: &str, b: &str) -> f32 {
+    let max_len = a.chars().count().max(b.chars().count());
+    if max_len == 0 {
+        return 1.0;
+    }
+    1.0 - levenshtein(a, b) as f32 / max_len as f32
+}
+
+/// Levenshtein edit distance between two strings, counted in characters.
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vocabulary(terms: &[&str]) -> Vocabulary {
+        Vocabulary::from_config(&VocabularyConfig {
+            terms: terms.iter().map(|term| term.to_string()).collect(),
+            ..VocabularyConfig::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn near_misses_are_replaced_by_the_term() {
+        let vocabulary = vocabulary(&["Kubernetes", "PostgreSQL"]);
+        assert_eq!(vocabulary.correct(" We deploy to kubernetis, daily."), " We deploy to Kubernetes, daily.");
+        assert_eq!(vocabulary.correct(" Backed by postgresql."), " Backed by PostgreSQL.");
+        assert_eq!(vocabulary.correct(" Backed by mysql"), " Backed by mysql");
+        assert_eq!(vocabulary.correct(" Nothing to fix"), " Nothing to fix");
+    }
+
+    #[test]
+    fn phrases_are_matched_across_words() {
+        let vocabulary = vocabulary(&["Visual Studio Code", "Code"]);
+        assert_eq!(vocabulary.correct(" open visual studio kode now"), " open Visual Studio Code now");
+    }
+
+    #[test]
+    fn everyday_words_are_not_taken_for_terms() {
+        let vocabulary = vocabulary(&["Slack", "Notion", "Jupyter"]);
+        assert_eq!(vocabulary.correct(" push to the stack in black"), " push to the stack in black");
+        assert_eq!(vocabulary.correct(" write it in slack"), " write it in Slack");
+        assert_eq!(vocabulary.correct(" a motion in notion"), " a motion in Notion");
+        assert_eq!(vocabulary.correct(" open jupiter"), " open Jupyter");
+    }
+
+    #[test]
+    fn exact_terms_win_over_near_misses_of_other_terms() {
+        let vocabulary = vocabulary(&["Kubernetes", "Kubernetas"]);
+        assert_eq!(vocabulary.correct(" run kubernetas"), " run Kubernetas");
+    }
+
+    #[test]
+    fn short_terms_need_an_exact_match() {
+        let vocabulary = vocabulary(&["AWS"]);
+        assert_eq!(vocabulary.correct(" on aws today"), " on AWS today");
+        assert_eq!(vocabulary.correct(" on was today"), " on was today");
+    }
+
+    #[test]
+    fn similarity_follows_the_edit_distance() {
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(similarity("abcd", "abcd"), 1.0);
+        assert_eq!(similarity("abcd", "abce"), 0.75);
+        assert_eq!(similarity("", ""), 1.0);
+    }
+}
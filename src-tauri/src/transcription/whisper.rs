@@ -1,149 +1,329 @@
-use std::sync::{Arc, Mutex};
-
-use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
 use whisper_rs::{
-    FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters, WhisperState,
+    FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters, WhisperError,
+    WhisperState,
 };
 
-/// Configuration constants
-const MIN_AUDIO_DURATION_SECONDS: f32 = 1.0;
-const SAMPLE_RATE: usize = 16_000;
-const DEFAULT_LANGUAGE: &str = "en";
+use crate::transcription::engine::{TranscribeOptions, TranscriptionEngine};
+use crate::transcription::segment::{TranscriptSegment, TranscriptToken};
+use crate::transcription::service::TranscriptionTask;
 
-/// Global Whisper state shared across the application
-static WHISPER_STATE: Lazy<Arc<Mutex<Option<WhisperState>>>> =
-    Lazy::new(|| Arc::new(Mutex::new(None)));
+/// Language value that enables automatic language detection
+pub const AUTO_LANGUAGE: &str = "auto";
+/// Language used by English-only models
+const ENGLISH: &str = "en";
+/// Upper bound on the default number of threads used for inference
+const MAX_THREADS: usize = 4;
 
-/// Global Whisper parameters
-static WHISPER_PARAMS: Lazy<Mutex<Option<FullParams>>> = Lazy::new(|| Mutex::new(None));
+/// How Whisper picks tokens while decoding
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DecodingStrategy {
+    /// Pick the most likely token, sampling `best_of` candidates when the temperature is above 0
+    #[default]
+    Greedy,
+    /// Keep `beam_size` hypotheses and pick the best at the end
+    BeamSearch,
+}
 
-/// Initializes the Whisper speech-to-text model with the specified model file.
-///
-/// This function loads the model from the provided path and configures it with
-/// default parameters optimized for English transcription.
-///
-/// # Arguments
-///
-/// * `model_path` - Path to the Whisper model file
-///
-/// # Returns
-///
-/// * `Result<(), String>` - Ok if successful, Err with error message otherwise
-pub fn init(model_path: &str) -> Result<(), String> {
-    // Create Whisper context
-    let ctx = WhisperContext::new_with_params(model_path, WhisperContextParameters::default())
-        .map_err(|e| format!("Failed to create Whisper context: {:?}", e))?;
-    
-    // Create state
-    let state = ctx.create_state()
-        .map_err(|e| format!("Failed to create Whisper state: {:?}", e))?;
-    
-    whisper_rs::install_whisper_tracing_trampoline();
-    
-    // Configure parameters
-    let mut params = FullParams::new(SamplingStrategy::default());
-    params.set_print_progress(false);
-    params.set_print_realtime(false);
-    params.set_print_special(false);
-    params.set_print_timestamps(false);
-    params.set_language(Some(DEFAULT_LANGUAGE));
-
-    // Store state and parameters
-    {
-        let mut global_state = WHISPER_STATE.lock()
-            .map_err(|_| "Failed to lock Whisper state".to_string())?;
-        *global_state = Some(state);
+/// Decoding parameters applied to every Whisper run
+#[derive(Debug, Clone)]
+pub struct DecodingConfig {
+    /// Greedy or beam search decoding
+    pub strategy: DecodingStrategy,
+    /// Number of candidates sampled by greedy decoding
+    pub best_of: u32,
+    /// Number of beams used by beam search
+    pub beam_size: u32,
+    /// Initial sampling temperature (0.0 = deterministic)
+    pub temperature: f32,
+    /// Temperature increase when decoding fails a quality check (0.0 disables fallback)
+    pub temperature_increment: f32,
+    /// Number of inference threads (None = number of cores, at most 4)
+    pub threads: Option<usize>,
+    /// Probability of the no-speech token above which a window is treated as silence
+    pub no_speech_threshold: f32,
+    /// Whether to suppress blank output at the start of a window
+    pub suppress_blank: bool,
+    /// Whether to suppress non-speech tokens such as music notes and brackets
+    pub suppress_non_speech_tokens: bool,
+    /// Maximum segment length in characters (0 = no limit)
+    pub max_segment_length: u32,
+}
+
+impl Default for DecodingConfig {
+    fn default() -> Self {
+        Self {
+            strategy: DecodingStrategy::Greedy,
+            best_of: 1,
+            beam_size: 5,
+            temperature: 0.0,
+            temperature_increment: 0.2,
+            threads: None,
+            no_speech_threshold: 0.6,
+            suppress_blank: true,
+            suppress_non_speech_tokens: false,
+            max_segment_length: 0,
+        }
     }
-    
-    {
-        let mut global_params = WHISPER_PARAMS.lock()
-            .map_err(|_| "Failed to lock Whisper parameters".to_string())?;
-        *global_params = Some(params);
+}
+
+impl DecodingConfig {
+    /// Checks that the parameters are within the ranges Whisper accepts.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - Ok if valid, Err describing the first invalid parameter
+    pub fn validate(&self) -> Result<(), String> {
+        if self.best_of == 0 {
+            return Err("decoding.best_of must be at least 1".to_string());
+        }
+        if self.beam_size == 0 {
+            return Err("decoding.beam_size must be at least 1".to_string());
+        }
+        // Above 2.0 sampling is close to uniform over the vocabulary and yields no usable text
+        if !(0.0..=2.0).contains(&self.temperature) {
+            return Err("decoding.temperature must be between 0.0 and 2.0".to_string());
+        }
+        // whisper.cpp stops falling back once the temperature passes 1.0, so a larger
+        // step would never be applied
+        if !(0.0..=1.0).contains(&self.temperature_increment) {
+            return Err("decoding.temperature_increment must be between 0.0 and 1.0".to_string());
+        }
+        if self.threads == Some(0) {
+            return Err("decoding.threads must be at least 1".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.no_speech_threshold) {
+            return Err("decoding.no_speech_threshold must be between 0.0 and 1.0".to_string());
+        }
+        Ok(())
     }
 
-    Ok(())
+    /// Number of threads to use for inference.
+    fn thread_count(&self) -> usize {
+        self.threads.unwrap_or_else(default_thread_count)
+    }
+
+    /// Builds the Whisper parameters for a run.
+    fn full_params<'a, 'b>(&self) -> FullParams<'a, 'b> {
+        let strategy = match self.strategy {
+            DecodingStrategy::Greedy => SamplingStrategy::Greedy {
+                best_of: self.best_of as i32,
+            },
+            DecodingStrategy::BeamSearch => SamplingStrategy::BeamSearch {
+                beam_size: self.beam_size as i32,
+                // Use Whisper's default patience
+                patience: -1.0,
+            },
+        };
+
+        let mut params = FullParams::new(strategy);
+        params.set_n_threads(self.thread_count() as i32);
+        params.set_temperature(self.temperature);
+        params.set_temperature_inc(self.temperature_increment);
+        params.set_no_speech_thold(self.no_speech_threshold);
+        params.set_suppress_blank(self.suppress_blank);
+        params.set_suppress_non_speech_tokens(self.suppress_non_speech_tokens);
+        if self.max_segment_length > 0 {
+            // Splitting by length relies on the token timestamps, which are always on
+            params.set_split_on_word(true);
+            params.set_max_len(self.max_segment_length as i32);
+        }
+        params
+    }
 }
 
-/// Transcribes the provided audio samples using the Whisper model.
-///
-/// This function uses the globally initialized Whisper state to transcribe
-/// the provided audio samples. The audio must be in 16kHz sampling rate format.
-/// Returns None if the audio is too short (less than 1 second).
-///
-/// # Arguments
-///
-/// * `samples` - Audio samples as f32 values (16kHz, mono)
+/// A loaded Whisper model together with its decoding state.
 ///
-/// # Returns
-///
-/// * `Option<String>` - Transcribed text if successful, None otherwise
-pub fn transcribe(samples: &[f32]) -> Option<String> {
-    let min_samples = (MIN_AUDIO_DURATION_SECONDS * SAMPLE_RATE as f32) as usize;
-    if samples.len() < min_samples {
-        println!("Less than {}s of audio. Skipping...", MIN_AUDIO_DURATION_SECONDS);
-        return None;
+/// Each engine owns its model, so several engines with different models can
+/// coexist in one process.
+pub struct WhisperEngine {
+    /// Loaded model
+    context: WhisperContext,
+    /// Decoding state created from the model
+    state: WhisperState,
+}
+
+impl WhisperEngine {
+    /// Loads the Whisper speech-to-text model from the specified model file.
+    ///
+    /// # Arguments
+    ///
+    /// * `model_path` - Path to the Whisper model file
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, String>` - The loaded engine, or an error message
+    pub fn load(model_path: &str) -> Result<Self, String> {
+        // Create Whisper context
+        let context = WhisperContext::new_with_params(model_path, WhisperContextParameters::default())
+            .map_err(|e| format!("Failed to create Whisper context: {:?}", e))?;
+
+        // Create state
+        let state = context.create_state()
+            .map_err(|e| format!("Failed to create Whisper state: {:?}", e))?;
+
+        whisper_rs::install_whisper_tracing_trampoline();
+
+        Ok(Self { context, state })
     }
 
-    // Get state and parameters
-    let state_lock = WHISPER_STATE.clone();
-    let mut state_guard = match state_lock.lock() {
-        Ok(guard) => guard,
-        Err(_) => {
-            println!("Failed to lock Whisper state");
-            return None;
-        }
-    };
-    
-    let state = match state_guard.as_mut() {
-        Some(state) => state,
-        None => {
-            println!("Whisper state not initialized");
-            return None;
-        }
-    };
-    
-    let params_guard = match WHISPER_PARAMS.lock() {
-        Ok(guard) => guard,
-        Err(_) => {
-            println!("Failed to lock Whisper parameters");
-            return None;
-        }
-    };
-    
-    let mut params = match params_guard.clone() {
-        Some(params) => params,
-        None => {
-            println!("Whisper parameters not initialized");
-            return None;
+    /// Detects the spoken language of the provided audio.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(&'static str, f32), String>` - Language code and its probability
+    fn detect_language(&mut self, samples: &[f32], threads: usize) -> Result<(&'static str, f32), String> {
+        self.state.pcm_to_mel(samples, threads)
+            .map_err(|e| format!("Failed to compute mel spectrogram: {:?}", e))?;
+        let (id, probabilities) = self.state.lang_detect(0, threads)
+            .map_err(|e| format!("Language detection failed: {:?}", e))?;
+
+        let language = whisper_rs::get_lang_str(id)
+            .ok_or_else(|| format!("Unknown language id {}", id))?;
+        let probability = probabilities.get(id as usize).copied().unwrap_or(0.0);
+        println!("Detected language {} ({:.2})", language, probability);
+        Ok((language, probability))
+    }
+}
+
+impl TranscriptionEngine for WhisperEngine {
+    fn name(&self) -> &str {
+        "whisper"
+    }
+
+    fn transcribe(
+        &mut self,
+        samples: &[f32],
+        options: &TranscribeOptions,
+    ) -> Result<Vec<TranscriptSegment>, String> {
+        let threads = options.decoding.thread_count();
+        let (language, task) = (options.language, options.task);
+
+        // Resolve the language for this window
+        let (language, language_probability) = if !self.context.is_multilingual() {
+            if language != ENGLISH {
+                println!("Model is English-only, ignoring language \"{}\"", language);
+            }
+            if task == TranscriptionTask::Translate {
+                println!("Model is English-only, translation has no effect");
+            }
+            (ENGLISH, None)
+        } else if language == AUTO_LANGUAGE {
+            let (detected, probability) = self.detect_language(samples, threads)?;
+            (detected, Some(probability))
+        } else {
+            (language, None)
+        };
+
+        // Configure parameters for this run
+        let mut params = options.decoding.full_params();
+        params.set_print_progress(false);
+        params.set_print_realtime(false);
+        params.set_print_special(false);
+        params.set_print_timestamps(false);
+        // Per-token times are merged into word timestamps and let whisper.cpp split
+        // segments by length
+        params.set_token_timestamps(true);
+        params.set_language(Some(language));
+        params.set_translate(task == TranscriptionTask::Translate);
+        if let Some(prompt) = options.initial_prompt {
+            params.set_initial_prompt(prompt);
         }
-    };
 
-    // Configure parameters for this run
-    params.set_print_progress(false);
-    params.set_print_realtime(false);
-    params.set_print_special(false);
-    params.set_print_timestamps(false);
-    params.set_language(Some(DEFAULT_LANGUAGE));
+        println!("Transcribing...");
 
-    println!("Transcribing...");
+        // Process the audio
+        self.state.full(params, samples)
+            .map_err(|e| format!("Failed to transcribe audio: {:?}", e))?;
 
-    // Process the audio
-    if let Err(err) = state.full(params, samples) {
-        println!("Failed to transcribe audio: {:?}", err);
-        return None;
+        // Collect every segment Whisper produced for this window
+        let mut segments = collect_segments(&self.state)
+            .map_err(|e| format!("Failed to read transcription result: {:?}", e))?;
+        for segment in segments.iter_mut() {
+            segment.language = language.to_string();
+            segment.language_probability = language_probability;
+            segment.task = task;
+        }
+        println!("Returned {} segments", segments.len());
+        Ok(segments)
     }
+}
+
+/// Checks whether Whisper supports the given language code ("auto" included).
+pub fn is_supported_language(language: &str) -> bool {
+    language == AUTO_LANGUAGE || whisper_rs::get_lang_id(language).is_some()
+}
+
+/// Number of threads used for inference.
+fn default_thread_count() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(MAX_THREADS)
+}
+
+/// Reads all segments of the last `full` run from the Whisper state.
+fn collect_segments(state: &WhisperState) -> Result<Vec<TranscriptSegment>, WhisperError> {
+    let n_segments = state.full_n_segments()?;
+    let mut segments = Vec::with_capacity(n_segments as usize);
 
-    println!("Got State");
-    
-    // Get the transcription result
-    match state.full_get_segment_text_lossy(0) {
-        Ok(text) => {
-            println!("Returned text");
-            Some(text)
-        },
-        Err(err) => {
-            println!("Failed to get segment text: {:?}", err);
-            None
+    for i in 0..n_segments {
+        let text = state.full_get_segment_text_lossy(i)?;
+        // Segment times are reported in centiseconds
+        let start = state.full_get_segment_t0(i)? as f32 / 100.0;
+        let end = state.full_get_segment_t1(i)? as f32 / 100.0;
+
+        let mut tokens = Vec::new();
+        let mut logprob_sum = 0.0;
+        for j in 0..state.full_n_tokens(i)? {
+            let token_text = state.full_get_token_text_lossy(i, j)?;
+            if is_special_token(&token_text) {
+                continue;
+            }
+            let data = state.full_get_token_data(i, j)?;
+            logprob_sum += data.plog;
+            // Token times are in centiseconds too; keep them inside the segment
+            let token_start = (data.t0 as f32 / 100.0).max(start).min(end);
+            let token_end = (data.t1 as f32 / 100.0).max(token_start).min(end);
+            tokens.push(TranscriptToken {
+                text: token_text,
+                probability: data.p,
+                start: token_start,
+                end: token_end,
+            });
         }
+
+        let avg_logprob = if tokens.is_empty() {
+            0.0
+        } else {
+            logprob_sum / tokens.len() as f32
+        };
+
+        segments.push(TranscriptSegment {
+            start,
+            end,
+            text,
+            tokens,
+            words: Vec::new(),
+            avg_logprob,
+            // whisper-rs does not expose the decoder's no-speech probability
+            no_speech_prob: None,
+            language: String::new(),
+            language_probability: None,
+            task: TranscriptionTask::Transcribe,
+            id: 0,
+            is_final: true,
+            speaker: None,
+            spoken_text: None,
+            redactions: Vec::new(),
+        });
     }
-} 
\ No newline at end of file
+
+    Ok(segments)
+}
+
+/// Checks whether a token is a Whisper control token such as `[_BEG_]` or `<|endoftext|>`.
+fn is_special_token(text: &str) -> bool {
+    text.starts_with("[_") || text.starts_with("<|")
+}
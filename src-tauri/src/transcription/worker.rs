@@ -0,0 +1,336 @@
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+use tokio::sync::mpsc as tokio_mpsc;
+
+use crate::transcription::diarization::{DiarizationConfig, SpeakerTracker};
+use crate::transcription::engine::EngineKind;
+use crate::transcription::loader::{self, StatusReporter};
+use crate::transcription::queue::{JobQueue, QueueConfig, QueueDecision};
+use crate::transcription::segment::TranscriptSegment;
+use crate::transcription::service::TranscriptionService;
+
+/// A window of processed audio waiting to be transcribed
+pub struct TranscriptionJob {
+    /// Audio samples at the transcription sample rate
+    pub samples: Vec<f32>,
+    /// Session time in seconds at which the window starts
+    pub window_start: f32,
+    /// Id of the window, shared by its interim and final results
+    pub segment_id: u64,
+    /// Whether the window is complete, or still growing and transcribed for an interim result
+    pub is_final: bool,
+}
+
+/// A switch to the fallback model, undone when the session ends
+struct FallbackSwitch {
+    /// Thread loading the fallback model
+    loader: thread::JoinHandle<()>,
+    /// Path of the fallback model
+    model_path: String,
+    /// Whisper model in use before the switch, None for other engines
+    original_model_path: Option<String>,
+}
+
+/// TranscriptionWorker runs Whisper inference on a dedicated thread.
+///
+/// Jobs are fed through a deadline-aware queue, so the async side never waits
+/// for inference while the CPU-bound work happens off the runtime.
+pub struct TranscriptionWorker {
+    /// Queue of windows waiting for the worker
+    queue: Arc<JobQueue>,
+    /// Handle to the worker thread, joined on shutdown
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl TranscriptionWorker {
+    /// Spawns the worker thread.
+    ///
+    /// The thread exits once the worker is dropped and all queued jobs are done,
+    /// which closes the result channel.
+    ///
+    /// # Arguments
+    ///
+    /// * `service` - Transcription service used for inference
+    /// * `queue` - Capacity, deadline and overload policy of the job queue
+    /// * `results` - Channel receiving the segments of each window, in session time
+    /// * `diarization` - Speaker labelling of final segments
+    /// * `decisions` - Channel receiving the decisions taken when transcription falls behind
+    /// * `model_status` - Receives status changes while the fallback model loads
+    pub fn spawn(
+        service: Arc<Mutex<TranscriptionService>>,
+        queue: QueueConfig,
+        results: tokio_mpsc::UnboundedSender<Vec<TranscriptSegment>>,
+        diarization: DiarizationConfig,
+        decisions: mpsc::Sender<QueueDecision>,
+        model_status: StatusReporter,
+    ) -> Self {
+        let queue = Arc::new(JobQueue::with_config(queue, decisions));
+
+        let thread_queue = queue.clone();
+        let thread = thread::Builder::new()
+            .name("transcription-worker".to_string())
+            .spawn(move || Self::run(service, thread_queue, results, diarization, model_status))
+            .expect("Failed to spawn transcription worker thread");
+
+        Self { queue, thread: Some(thread) }
+    }
+
+    /// Queues a window for transcription without waiting; if the worker falls
+    /// behind, the queue's overload policy makes room.
+    ///
+    /// # Arguments
+    ///
+    /// * `job` - The window to transcribe
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - Ok if queued, Err if the worker has stopped
+    pub fn submit(&self, job: TranscriptionJob) -> Result<(), String> {
+        self.queue.push(job).map(|_| ())
+    }
+
+    /// Queues an interim job, but only if no other job is waiting, so interim
+    /// results never hold up final ones.
+    ///
+    /// # Arguments
+    ///
+    /// * `job` - The in-progress window to transcribe
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether the job was queued
+    pub fn submit_interim(&self, job: TranscriptionJob) -> bool {
+        self.queue.push(job).unwrap_or(false)
+    }
+
+    /// Stops accepting windows and waits until the queued ones are transcribed and
+    /// their results sent. Blocks for as long as that takes, so call it off the
+    /// async runtime.
+    pub fn shutdown(mut self) {
+        self.queue.close();
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                println!("Transcription worker panicked");
+            }
+        }
+    }
+
+    /// Worker thread loop: transcribes jobs until the queue is closed and empty.
+    fn run(
+        service: Arc<Mutex<TranscriptionService>>,
+        queue: Arc<JobQueue>,
+        results: tokio_mpsc::UnboundedSender<Vec<TranscriptSegment>>,
+        diarization: DiarizationConfig,
+        model_status: StatusReporter,
+    ) {
+        let mut fallback: Option<FallbackSwitch> = None;
+        // Speakers are tracked for the lifetime of the worker, i.e. one session
+        let sample_rate = diarization.sample_rate;
+        let mut speakers = diarization.enabled.then(|| SpeakerTracker::with_config(diarization));
+
+        while let Some(job) = queue.pop() {
+            let started = Instant::now();
+            // The service is only held around inference, so commands stay responsive
+            let segments = TranscriptionService::transcribe_shared(&service, &job.samples, job.is_final);
+            // An interim hypothesis is shown as one piece until the final result replaces it
+            let segments = if job.is_final {
+                segments
+            } else {
+                segments.and_then(TranscriptSegment::merge).map(|segment| vec![segment])
+            };
+
+            // Load the faster model next to the running one; it takes over between windows
+            let audio_seconds = job.samples.len() as f32 / sample_rate as f32;
+            if let Some(model_path) = queue.record(audio_seconds, started.elapsed()) {
+                let original_model_path = {
+                    let service = service.lock().unwrap();
+                    let config = service.config();
+                    (config.engine == EngineKind::Whisper).then(|| config.model_path.clone())
+                };
+                let (service, report, path) = (service.clone(), model_status.clone(), model_path.clone());
+                let spawned = thread::Builder::new()
+                    .name("fallback-model-loader".to_string())
+                    .spawn(move || {
+                        if let Err(err) = loader::load_model(&service, Some(path), &*report) {
+                            println!("Failed to load fallback model: {}", err);
+                        }
+                    });
+                match spawned {
+                    Ok(loader) => fallback = Some(FallbackSwitch { loader, model_path, original_model_path }),
+                    Err(err) => println!("Failed to spawn fallback model loader: {}", err),
+                }
+            }
+
+            let segments = match segments {
+                Some(mut segments) if !segments.is_empty() => {
+                    // Interim segments keep no speaker; their final result gets one
+                    if let (Some(speakers), true) = (speakers.as_mut(), job.is_final) {
+                        speakers.assign(&mut segments, &job.samples);
+                    }
+                    for segment in segments.iter_mut() {
+                        segment.shift(job.window_start);
+                        segment.id = job.segment_id;
+                        segment.is_final = job.is_final;
+                    }
+                    segments
+                }
+                // Every window gets a final result, so its interim text never lingers
+                _ if job.is_final => {
+                    vec![TranscriptSegment::empty(job.segment_id, job.window_start, job.window_start + audio_seconds)]
+                }
+                _ => continue,
+            };
+            if results.send(segments).is_err() {
+                println!("Transcription results receiver dropped");
+                break;
+            }
+        }
+        println!("Transcription worker stopped");
+
+        if let Some(fallback) = fallback {
+            Self::restore_model(service, fallback, model_status);
+        }
+    }
+
+    /// Switches back to the model the session started with once the fallback has
+    /// loaded, in the background. Nothing is restored if the fallback is no longer
+    /// in use, e.g. because the user switched models since.
+    fn restore_model(service: Arc<Mutex<TranscriptionService>>, fallback: FallbackSwitch, model_status: StatusReporter) {
+        let Some(original_model_path) = fallback.original_model_path else {
+            return;
+        };
+        let spawned = thread::Builder::new()
+            .name("model-restorer".to_string())
+            .spawn(move || {
+                if fallback.loader.join().is_err() {
+                    println!("Fallback model loader panicked");
+                }
+                if service.lock().unwrap().config().model_path != fallback.model_path {
+                    return;
+                }
+                println!("Session ended, switching back to {}", original_model_path);
+                if let Err(err) = loader::load_model(&service, Some(original_model_path), &*model_status) {
+                    println!("Failed to restore model: {}", err);
+                }
+            });
+        if let Err(err) = spawned {
+            println!("Failed to spawn model restorer: {}", err);
+        }
+    }
+}
+
+impl Drop for TranscriptionWorker {
+    fn drop(&mut self) {
+        // Without shutdown, the thread still finishes the queued windows and exits on its own
+        self.queue.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcription::mock::{MockEngine, MockEngineConfig};
+    use crate::transcription::service::TranscriptionConfig;
+    use std::collections::HashMap;
+
+    /// One second of constant audio; different levels give different fingerprints.
+    fn window(level: f32) -> Vec<f32> {
+        vec![level; 16000]
+    }
+
+    fn mock_worker(
+        script: &[(&[f32], &str)],
+    ) -> (TranscriptionWorker, tokio_mpsc::UnboundedReceiver<Vec<TranscriptSegment>>) {
+        let responses_by_input: HashMap<u64, String> = script
+            .iter()
+            .map(|(samples, text)| (MockEngine::fingerprint(samples), text.to_string()))
+            .collect();
+        let mut service = TranscriptionService::with_config(TranscriptionConfig {
+            engine: EngineKind::Mock,
+            mock: MockEngineConfig { responses_by_input, ..MockEngineConfig::default() },
+            ..TranscriptionConfig::default()
+        });
+        service.initialize().unwrap();
+
+        let (results, receiver) = tokio_mpsc::unbounded_channel();
+        let (decisions, _) = mpsc::channel();
+        let worker = TranscriptionWorker::spawn(
+            Arc::new(Mutex::new(service)),
+            QueueConfig::default(),
+            results,
+            DiarizationConfig::default(),
+            decisions,
+            Arc::new(|_| {}),
+        );
+        (worker, receiver)
+    }
+
+    fn job(samples: Vec<f32>, window_start: f32, segment_id: u64, is_final: bool) -> TranscriptionJob {
+        TranscriptionJob { samples, window_start, segment_id, is_final }
+    }
+
+    fn text(segments: &[TranscriptSegment]) -> String {
+        segments.iter().map(|segment| segment.text.as_str()).collect::<String>().trim().to_lowercase()
+    }
+
+    #[test]
+    fn windows_get_their_scripted_text_in_session_time() {
+        let first = window(0.1);
+        let second = window(0.2);
+        let (worker, mut results) = mock_worker(&[(&first, "first window"), (&second, "second window")]);
+
+        worker.submit(job(first, 0.0, 0, true)).unwrap();
+        worker.submit(job(second, 1.0, 1, true)).unwrap();
+        worker.shutdown();
+
+        let first = results.blocking_recv().unwrap();
+        assert_eq!(text(&first), "first window");
+        assert_eq!(first[0].id, 0);
+        assert!(first[0].is_final);
+
+        let second = results.blocking_recv().unwrap();
+        assert_eq!(text(&second), "second window");
+        assert_eq!(second[0].id, 1);
+        assert_eq!(second[0].start, 1.0);
+        assert!(results.blocking_recv().is_none());
+    }
+
+    #[test]
+    fn interim_results_do_not_shift_the_script() {
+        let partial = window(0.3);
+        let complete = window(0.4);
+        let (worker, mut results) = mock_worker(&[(&partial, "hello"), (&complete, "hello world")]);
+
+        assert!(worker.submit_interim(job(partial, 0.0, 0, false)));
+        let interim = results.blocking_recv().unwrap();
+        assert_eq!(text(&interim), "hello");
+        assert!(!interim[0].is_final);
+
+        worker.submit(job(complete, 0.0, 0, true)).unwrap();
+        worker.shutdown();
+        let last = results.blocking_recv().unwrap();
+        assert_eq!(text(&last), "hello world");
+        assert!(last[0].is_final);
+    }
+
+    #[test]
+    fn windows_without_text_still_get_a_final_result() {
+        let partial = window(0.5);
+        let silent = window(0.6);
+        let (worker, mut results) = mock_worker(&[(&partial, "hmm")]);
+
+        assert!(worker.submit_interim(job(partial, 2.0, 3, false)));
+        assert_eq!(text(&results.blocking_recv().unwrap()), "hmm");
+
+        worker.submit(job(silent, 2.0, 3, true)).unwrap();
+        worker.shutdown();
+        let last = results.blocking_recv().unwrap();
+        assert_eq!(last.len(), 1);
+        assert!(last[0].text.is_empty());
+        assert_eq!(last[0].id, 3);
+        assert!(last[0].is_final);
+        assert_eq!((last[0].start, last[0].end), (2.0, 3.0));
+    }
+}